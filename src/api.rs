@@ -1,80 +1,557 @@
-use anyhow::Result;
-use serde::Deserialize;
+//! Rate-limited CoinGecko API client and the market data it returns
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+use thiserror::Error;
+
+use crate::cache::{Cache, Cached};
 
 const COINGECKO_API_BASE: &str = "https://api.coingecko.com/api/v3";
+const COINGECKO_PRO_API_BASE: &str = "https://pro-api.coingecko.com/api/v3";
+
+// Retry / rate limit settings for the public API tier
+const MAX_RETRIES: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(16);
+const MAX_INLINE_WAIT: Duration = Duration::from_secs(10); // Longer waits become a cooldown instead of blocking
+const BUDGET_WINDOW: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ERROR_BODY: usize = 200;
+
+/// Upper bounds in seconds of the fetch latency histogram buckets
+pub const LATENCY_BUCKETS: [f64; 8] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Errors returned by `CoinGeckoClient`
+#[derive(Debug, Clone, Error)]
+#[non_exhaustive]
+pub enum ApiError {
+    /// The request couldn't be sent or the response couldn't be read
+    #[error("network error: {0}")]
+    Network(String),
+    /// No response within the client's timeout
+    #[error("request timed out after {}s", .0.as_secs())]
+    Timeout(Duration),
+    /// The request budget is used up or CoinGecko answered `429`
+    #[error("rate limited, retry in {}s", .retry_after.as_secs())]
+    RateLimited {
+        /// Time until requests are allowed again
+        retry_after: Duration,
+    },
+    /// CoinGecko answered with an unsuccessful status
+    #[error("HTTP {status}: {body}")]
+    Status {
+        /// HTTP status code
+        status: u16,
+        /// Start of the response body
+        body: String,
+    },
+    /// The response didn't match the expected format
+    #[error("failed to decode `{field}`: {message}")]
+    Decode {
+        /// Path of the offending field, e.g. `[3].current_price`
+        field: String,
+        /// What was wrong with it
+        message: String,
+    },
+    /// CoinGecko doesn't support the requested `vs_currency`
+    #[error("invalid currency `{0}`")]
+    InvalidCurrency(String),
+    /// An offline client has no cached response for the request
+    #[error("no cached data available offline")]
+    NotCached,
+}
+
+impl ApiError {
+    /// Short, user-facing suggestion for resolving the error
+    pub fn hint(&self) -> &'static str {
+        match self {
+            ApiError::Network(_) => "Check your internet connection",
+            ApiError::Timeout(_) => "CoinGecko is slow to respond, try again",
+            ApiError::RateLimited { .. } => "Too many requests, waiting before retrying",
+            ApiError::Status { status, .. } if *status >= 500 => "CoinGecko is having problems, try again later",
+            ApiError::Status { .. } => "The request was rejected by CoinGecko",
+            ApiError::Decode { .. } => "Unexpected API response, the format may have changed",
+            ApiError::InvalidCurrency(_) => "Use a currency supported by CoinGecko, e.g. usd or eur",
+            ApiError::NotCached => "Run once without --offline to populate the cache",
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ApiError::Timeout(REQUEST_TIMEOUT)
+        } else {
+            ApiError::Network(e.without_url().to_string())
+        }
+    }
+}
+
+/// Result of a `CoinGeckoClient` request
+pub type Result<T> = std::result::Result<T, ApiError>;
+
+/// CoinGecko API plan, which decides the endpoint, auth header and rate limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiPlan {
+    /// Keyless public API
+    Public,
+    /// Free Demo plan key
+    Demo,
+    /// Paid Pro plan key
+    Pro,
+}
+
+impl ApiPlan {
+    /// Display name, e.g. `Demo`
+    pub fn label(&self) -> &'static str {
+        match self {
+            ApiPlan::Public => "Public",
+            ApiPlan::Demo => "Demo",
+            ApiPlan::Pro => "Pro",
+        }
+    }
+
+    fn base_url(&self) -> &'static str {
+        match self {
+            ApiPlan::Public | ApiPlan::Demo => COINGECKO_API_BASE,
+            ApiPlan::Pro => COINGECKO_PRO_API_BASE,
+        }
+    }
+
+    fn key_header(&self) -> Option<&'static str> {
+        match self {
+            ApiPlan::Public => None,
+            ApiPlan::Demo => Some("x-cg-demo-api-key"),
+            ApiPlan::Pro => Some("x-cg-pro-api-key"),
+        }
+    }
+
+    /// Requests allowed per minute
+    pub fn requests_per_minute(&self) -> usize {
+        match self {
+            ApiPlan::Public => 10,
+            ApiPlan::Demo => 30,
+            ApiPlan::Pro => 500,
+        }
+    }
+
+    /// How often the dashboard refreshes on this plan
+    pub fn refresh_interval(&self) -> Duration {
+        match self {
+            ApiPlan::Public => Duration::from_secs(60),
+            ApiPlan::Demo => Duration::from_secs(30),
+            ApiPlan::Pro => Duration::from_secs(10),
+        }
+    }
+}
+
+impl std::str::FromStr for ApiPlan {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "public" => Ok(ApiPlan::Public),
+            "demo" => Ok(ApiPlan::Demo),
+            "pro" => Ok(ApiPlan::Pro),
+            _ => Err(format!("Unknown API plan `{}`, expected public, demo or pro", s)),
+        }
+    }
+}
 
+/// CoinGecko API key together with the plan it belongs to
+#[derive(Clone)]
+pub struct ApiKey {
+    plan: ApiPlan,
+    key: String,
+}
+
+impl ApiKey {
+    /// Wrap a key for `plan`
+    pub fn new(plan: ApiPlan, key: String) -> Self {
+        Self { plan, key }
+    }
+}
+
+// Keep the key itself out of debug output
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKey").field("plan", &self.plan).finish_non_exhaustive()
+    }
+}
+
+/// One coin from the `/coins/markets` endpoint, quoted in the requested currency
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct CoinMarket {
+    /// CoinGecko id, e.g. `bitcoin`
+    pub id: String,
+    /// Ticker symbol in lowercase, e.g. `btc`
     pub symbol: String,
+    /// Display name, e.g. `Bitcoin`
     pub name: String,
+    /// Price in the quote currency
     pub current_price: Option<f64>,
+    /// Market capitalisation in the quote currency
     pub market_cap: Option<f64>,
+    /// Rank by market capitalisation, starting at 1
     pub market_cap_rank: Option<u32>,
+    /// Price times max (or total) supply
+    pub fully_diluted_valuation: Option<f64>,
+    /// Trading volume over 24 hours
     pub total_volume: Option<f64>,
+    /// Highest price in the last 24 hours
     pub high_24h: Option<f64>,
+    /// Lowest price in the last 24 hours
     pub low_24h: Option<f64>,
+    /// Price change over 24 hours in percent
     pub price_change_percentage_24h: Option<f64>,
+    /// Price change over 1 hour in percent
     pub price_change_percentage_1h_in_currency: Option<f64>,
+    /// Price change over 24 hours in percent, in the quote currency
     pub price_change_percentage_24h_in_currency: Option<f64>,
+    /// Price change over 7 days in percent
     pub price_change_percentage_7d_in_currency: Option<f64>,
+    /// Price change over 14 days in percent
     pub price_change_percentage_14d_in_currency: Option<f64>,
+    /// Price change over 30 days in percent
     pub price_change_percentage_30d_in_currency: Option<f64>,
+    /// Price change over 60 days in percent
     pub price_change_percentage_60d_in_currency: Option<f64>,
+    /// Price change over 200 days in percent
     pub price_change_percentage_200d_in_currency: Option<f64>,
+    /// Price change over 1 year in percent
     pub price_change_percentage_1y_in_currency: Option<f64>,
+    /// Coins in circulation
     pub circulating_supply: Option<f64>,
+    /// Coins that exist now
     pub total_supply: Option<f64>,
+    /// Maximum number of coins, if capped
     pub max_supply: Option<f64>,
+    /// All-time high price
     pub ath: Option<f64>,
+    /// Distance from the all-time high in percent
+    pub ath_change_percentage: Option<f64>,
+    /// RFC 3339 date of the all-time high
     pub ath_date: Option<String>,
+    /// All-time low price
     pub atl: Option<f64>,
+    /// RFC 3339 date of the all-time low
     pub atl_date: Option<String>,
+    /// RFC 3339 time CoinGecko last updated this entry
     pub last_updated: Option<String>,
+    /// Hourly prices over the last 7 days, oldest first
     #[serde(default)]
     pub sparkline_in_7d: Option<SparklineData>,
 }
 
+/// Price sparkline attached to a `CoinMarket`
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct SparklineData {
+    /// Prices, evenly spaced and oldest first
     pub price: Vec<f64>,
 }
 
+/// Snapshot of the client's rate limit state, used by the UI
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimitStatus {
+    /// Time left before requests are allowed again, if cooling down
+    pub cooldown: Option<Duration>,
+    /// Requests sent within the current budget window
+    pub used: usize,
+    /// Maximum requests allowed per budget window
+    pub budget: usize,
+}
+
+/// Outcome and latency counters of network fetches for one endpoint
+#[derive(Debug, Clone, Default)]
+pub struct FetchMetrics {
+    /// Fetches that returned data
+    pub successes: u64,
+    /// Fetches that ended in an error
+    pub failures: u64,
+    /// Cumulative number of fetches at or below each of `LATENCY_BUCKETS`
+    pub latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    /// Total time spent in fetches
+    pub latency_sum: Duration,
+}
+
+impl FetchMetrics {
+    fn observe(&mut self, elapsed: Duration, success: bool) {
+        if success {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+        for (count, bound) in self.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if elapsed.as_secs_f64() <= bound {
+                *count += 1;
+            }
+        }
+        self.latency_sum += elapsed;
+    }
+}
+
+#[derive(Debug)]
+struct RateLimiter {
+    budget: usize,
+    sent: VecDeque<Instant>,
+    cooldown_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            sent: VecDeque::new(),
+            cooldown_until: None,
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(&first) = self.sent.front() {
+            if now.duration_since(first) >= BUDGET_WINDOW {
+                self.sent.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.cooldown_until.is_some_and(|until| until <= now) {
+            self.cooldown_until = None;
+        }
+    }
+
+    /// Reserve a slot for one request, or return how long to wait
+    fn acquire(&mut self) -> std::result::Result<(), Duration> {
+        let now = Instant::now();
+        self.prune(now);
+
+        if let Some(until) = self.cooldown_until {
+            return Err(until - now);
+        }
+        if self.sent.len() >= self.budget {
+            let oldest = self.sent[0];
+            let until = oldest + BUDGET_WINDOW;
+            self.cooldown_until = Some(until);
+            return Err(until - now);
+        }
+
+        self.sent.push_back(now);
+        Ok(())
+    }
+
+    fn cool_down(&mut self, wait: Duration) {
+        let until = Instant::now() + wait;
+        self.cooldown_until = Some(self.cooldown_until.map_or(until, |u| u.max(until)));
+    }
+
+    fn status(&mut self) -> RateLimitStatus {
+        let now = Instant::now();
+        self.prune(now);
+        RateLimitStatus {
+            cooldown: self.cooldown_until.map(|until| until - now),
+            used: self.sent.len(),
+            budget: self.budget,
+        }
+    }
+}
+
+/// CoinGecko client with a per-plan request budget, retries and an optional on-disk cache.
+///
+/// Clones share the rate limiter and fetch metrics, so one client can be cloned into
+/// background tasks without exceeding the plan's limits.
 #[derive(Debug, Clone)]
 pub struct CoinGeckoClient {
     client: reqwest::Client,
+    api_key: Option<ApiKey>,
+    limiter: Arc<Mutex<RateLimiter>>,
+    /// Fetch metrics keyed by endpoint path, e.g. `/coins/markets`
+    metrics: Arc<Mutex<BTreeMap<String, FetchMetrics>>>,
+    cache: Option<Cache>,
+    offline: bool,
 }
 
 impl CoinGeckoClient {
+    /// Client for the keyless public API
     pub fn new() -> Self {
+        Self::with_api_key(None)
+    }
+
+    /// Create a client for a Demo or Pro plan; `None` uses the public API
+    pub fn with_api_key(api_key: Option<ApiKey>) -> Self {
         let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
+            .timeout(REQUEST_TIMEOUT)
             .user_agent("coins-cli/0.1.0")
             .build()
             .expect("Failed to create HTTP client");
+        let plan = api_key.as_ref().map_or(ApiPlan::Public, |k| k.plan);
         
-        Self { client }
+        Self {
+            client,
+            api_key,
+            limiter: Arc::new(Mutex::new(RateLimiter::new(plan.requests_per_minute()))),
+            metrics: Arc::new(Mutex::new(BTreeMap::new())),
+            cache: None,
+            offline: false,
+        }
+    }
+
+    /// Store successful responses in `cache`
+    pub fn with_cache(mut self, cache: Option<Cache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Serve every request from the cache without touching the network
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Plan of the configured key, `Public` without one
+    pub fn plan(&self) -> ApiPlan {
+        self.api_key.as_ref().map_or(ApiPlan::Public, |k| k.plan)
+    }
+
+    /// Current request budget usage and cooldown
+    pub fn rate_limit_status(&self) -> RateLimitStatus {
+        self.limiter.lock().unwrap().status()
+    }
+
+    /// Success/failure counts and latencies of network fetches so far, by endpoint
+    pub fn fetch_metrics(&self) -> BTreeMap<String, FetchMetrics> {
+        self.metrics.lock().unwrap().clone()
     }
 
     /// Fetch top 100 coins by market cap with price change percentages
     pub async fn get_markets(&self, vs_currency: &str) -> Result<Vec<CoinMarket>> {
-        let url = format!(
-            "{}/coins/markets?vs_currency={}&order=market_cap_desc&per_page=100&page=1&sparkline=true&price_change_percentage=1h,24h,7d,14d,30d,60d,200d,1y",
-            COINGECKO_API_BASE, vs_currency
+        check_currency(self.get_json(&markets_path(vs_currency)).await, vs_currency)
+    }
+
+    /// Fetch market data for specific coins by CoinGecko id, e.g. `solana`
+    pub async fn get_markets_by_ids(&self, vs_currency: &str, ids: &[String]) -> Result<Vec<CoinMarket>> {
+        let path = format!("{}&{}", markets_path(vs_currency), query(&[("ids", &ids.join(","))]));
+        check_currency(self.get_json(&path).await, vs_currency)
+    }
+
+    /// Currency codes accepted as `vs_currency`, e.g. `usd`, `eur`, `btc`
+    pub async fn get_supported_vs_currencies(&self) -> Result<Vec<String>> {
+        self.get_json("/simple/supported_vs_currencies").await
+    }
+
+    /// Prices of coins (by id) in each of `vs_currencies`, keyed by id then currency
+    pub async fn get_simple_prices(
+        &self,
+        ids: &[String],
+        vs_currencies: &[String],
+    ) -> Result<HashMap<String, HashMap<String, f64>>> {
+        let path = format!(
+            "/simple/price?{}",
+            query(&[("ids", &ids.join(",")), ("vs_currencies", &vs_currencies.join(","))])
         );
+        let prices: HashMap<String, HashMap<String, Option<f64>>> = self.get_json(&path).await?;
 
-        let response = self.client
-            .get(&url)
-            .header("Accept", "application/json")
-            .header("User-Agent", "coins-cli/0.1.0")
-            .send()
-            .await?;
+        Ok(prices
+            .into_iter()
+            .map(|(id, quotes)| {
+                let quotes = quotes.into_iter().filter_map(|(vs, p)| Some((vs, p?))).collect();
+                (id, quotes)
+            })
+            .collect())
+    }
+
+    /// Last cached `get_markets` response, regardless of age
+    pub fn cached_markets(&self, vs_currency: &str) -> Option<Cached<Vec<CoinMarket>>> {
+        self.cached(&markets_path(vs_currency))
+    }
+
+    fn cached<T: DeserializeOwned>(&self, path: &str) -> Option<Cached<T>> {
+        let entry = self.cache.as_ref()?.get(path)?;
+        Some(Cached {
+            data: decode(&entry.data).ok()?,
+            fetched_at: entry.fetched_at,
+        })
+    }
 
-        if !response.status().is_success() {
-            anyhow::bail!("API request failed with status: {}", response.status());
+    /// GET `path` and decode the JSON body, caching it on success.
+    /// Offline clients answer from the cache only.
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        if self.offline {
+            return self.cached(path).map(|c| c.data).ok_or(ApiError::NotCached);
         }
 
-        let coins: Vec<CoinMarket> = response.json().await?;
-        Ok(coins)
+        let url = format!("{}{}", self.plan().base_url(), path);
+        let started = Instant::now();
+        let result = match self.send(&url).await {
+            Ok(response) => response.bytes().await.map_err(ApiError::from),
+            Err(e) => Err(e),
+        };
+        let result = result.and_then(|body| Ok((decode(&body)?, body)));
+
+        let endpoint = path.split('?').next().unwrap_or(path).to_string();
+        self.metrics
+            .lock()
+            .unwrap()
+            .entry(endpoint)
+            .or_default()
+            .observe(started.elapsed(), result.is_ok());
+        let (data, body) = result?;
+
+        if let Some(cache) = &self.cache {
+            // A failed cache write shouldn't fail the request
+            let _ = cache.put(path, &body);
+        }
+        Ok(data)
+    }
+
+    /// Send a GET request, retrying 429/5xx responses and transient network errors
+    /// with jittered exponential backoff. `Retry-After` is honoured when present.
+    async fn send(&self, url: &str) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+
+        loop {
+            if let Err(wait) = self.limiter.lock().unwrap().acquire() {
+                return Err(ApiError::RateLimited { retry_after: wait });
+            }
+
+            let mut request = self.client
+                .get(url)
+                .header("Accept", "application/json");
+            if let Some(api_key) = &self.api_key {
+                if let Some(header) = api_key.plan.key_header() {
+                    request = request.header(header, &api_key.key);
+                }
+            }
+            let result = request.send().await;
+
+            let delay = match result {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = parse_retry_after(response.headers());
+                    let delay = retry_after.unwrap_or_else(|| backoff(attempt));
+
+                    if status == StatusCode::TOO_MANY_REQUESTS && (attempt >= MAX_RETRIES || delay > MAX_INLINE_WAIT) {
+                        let wait = retry_after.unwrap_or(BUDGET_WINDOW);
+                        self.limiter.lock().unwrap().cool_down(wait);
+                        return Err(ApiError::RateLimited { retry_after: wait });
+                    }
+                    if !is_retryable(status) || attempt >= MAX_RETRIES || delay > MAX_INLINE_WAIT {
+                        return Err(status_error(status, response).await);
+                    }
+                    delay
+                }
+                Err(e) if (e.is_timeout() || e.is_connect()) && attempt < MAX_RETRIES => backoff(attempt),
+                Err(e) => return Err(e.into()),
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
     }
 }
 
@@ -83,3 +560,138 @@ impl Default for CoinGeckoClient {
         Self::new()
     }
 }
+
+/// Find a coin by id, symbol or name (in that order, ignoring case).
+/// Coins are ranked by market cap, so the largest coin wins when symbols clash.
+pub fn find_coin<'a>(coins: &'a [CoinMarket], query: &str) -> Option<&'a CoinMarket> {
+    coins
+        .iter()
+        .find(|c| c.id.eq_ignore_ascii_case(query))
+        .or_else(|| coins.iter().find(|c| c.symbol.eq_ignore_ascii_case(query)))
+        .or_else(|| coins.iter().find(|c| c.name.eq_ignore_ascii_case(query)))
+}
+
+fn markets_path(vs_currency: &str) -> String {
+    format!(
+        "/coins/markets?{}&order=market_cap_desc&per_page=100&page=1&sparkline=true&price_change_percentage=1h,24h,7d,14d,30d,60d,200d,1y",
+        query(&[("vs_currency", vs_currency)])
+    )
+}
+
+/// URL-encoded query string of `params`, for values that come from the user
+fn query(params: &[(&str, &str)]) -> String {
+    let mut url = reqwest::Url::parse("http://localhost/").expect("valid URL");
+    url.query_pairs_mut().extend_pairs(params);
+    url.query().unwrap_or_default().to_string()
+}
+
+/// CoinGecko answers an unknown `vs_currency` with a 400
+fn check_currency<T>(result: Result<T>, vs_currency: &str) -> Result<T> {
+    match result {
+        Err(ApiError::Status { status: 400, body }) if body.contains("vs_currency") => {
+            Err(ApiError::InvalidCurrency(vs_currency.to_string()))
+        }
+        result => result,
+    }
+}
+
+/// Decode a JSON body, reporting the path of any field that fails
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    let de = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(de).map_err(|e| ApiError::Decode {
+        field: e.path().to_string(),
+        message: e.into_inner().to_string(),
+    })
+}
+
+async fn status_error(status: StatusCode, response: reqwest::Response) -> ApiError {
+    let body = response.text().await.unwrap_or_default();
+    ApiError::Status {
+        status: status.as_u16(),
+        body: body.trim().chars().take(MAX_ERROR_BODY).collect(),
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Exponential backoff with "equal jitter": half fixed, half random
+fn backoff(attempt: u32) -> Duration {
+    let exp = BASE_BACKOFF.saturating_mul(1 << attempt.min(16)).min(MAX_BACKOFF);
+    let half = exp / 2;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    half + half.mul_f64(nanos as f64 / 1_000_000_000.0)
+}
+
+/// Parse a `Retry-After` header given either as delay-seconds or as an HTTP date
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds();
+    Some(Duration::from_secs(secs.max(0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry_after(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(reqwest::header::RETRY_AFTER, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(parse_retry_after(&retry_after("120")), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(&retry_after(" 0 ")), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_as_http_date() {
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = parse_retry_after(&retry_after(&date)).unwrap();
+        assert!((88..=90).contains(&wait.as_secs()), "{:?}", wait);
+        // Dates in the past mean retry right away
+        assert_eq!(parse_retry_after(&retry_after("Wed, 21 Oct 2015 07:28:00 GMT")), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_malformed_or_missing() {
+        assert_eq!(parse_retry_after(&retry_after("soon")), None);
+        assert_eq!(parse_retry_after(&retry_after("-5")), None);
+        assert_eq!(parse_retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_up_to_the_cap() {
+        for attempt in 0..8 {
+            let exp = BASE_BACKOFF.saturating_mul(1 << attempt).min(MAX_BACKOFF);
+            let delay = backoff(attempt);
+            assert!(delay >= exp / 2 && delay <= exp, "attempt {}: {:?}", attempt, delay);
+        }
+        assert!(backoff(u32::MAX) <= MAX_BACKOFF);
+    }
+
+    #[test]
+    fn query_encodes_user_values() {
+        assert_eq!(query(&[("ids", "bitcoin,ethereum")]), "ids=bitcoin%2Cethereum");
+        assert_eq!(query(&[("ids", "a&b=c #d+e")]), "ids=a%26b%3Dc+%23d%2Be");
+        assert_eq!(query(&[("ids", "x"), ("vs_currencies", "usd,eur")]), "ids=x&vs_currencies=usd%2Ceur");
+    }
+
+    #[test]
+    fn markets_path_keeps_its_cache_key() {
+        assert!(markets_path("usd").starts_with("/coins/markets?vs_currency=usd&order=market_cap_desc"));
+        assert!(markets_path("e&r").starts_with("/coins/markets?vs_currency=e%26r&order="));
+    }
+}
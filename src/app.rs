@@ -1,52 +1,251 @@
-use crate::api::CoinMarket;
+use crate::api::{ApiError, ApiPlan, CoinMarket, RateLimitStatus};
+use crate::chart::{sparkline_series, ChartTimeframe};
+use crate::convert::{self, FiatRates, Unit};
+use crate::converter::ConverterDialog;
+use crate::export::ExportDialog;
+use crate::field::{sort_coins, Field};
+use crate::keymap::{Action, KeyChord, Keymap, Lookup};
+use crate::layout::ScreenLayout;
+use crate::palette::CommandPalette;
+use crate::recorder::Recorder;
+use crate::theme::{ChangeColors, ColorDepth, Theme};
+use crate::widgets::{Column, CoinTableState, HeatmapState, PriceChart, SparklineStyle, WidgetStyle};
+use chrono::{DateTime, Utc};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+use std::path::PathBuf;
 use std::time::Instant;
 
+/// Result of a key press inside a popup dialog
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ChartTimeframe {
-    OneHour,
-    TwentyFourHours,
-    SevenDays,
+pub enum DialogAction {
+    None,
+    Cancel,
+    Submit,
 }
 
-impl ChartTimeframe {
-    pub fn label(&self) -> &'static str {
-        match self {
-            ChartTimeframe::OneHour => "1H",
-            ChartTimeframe::TwentyFourHours => "24H",
-            ChartTimeframe::SevenDays => "7D",
-        }
-    }
+/// Short-lived notification shown above the footer
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub message: String,
+    pub is_error: bool,
+    pub shown_at: Instant,
+}
+
+/// What the left side of the screen shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Table,
+    /// Treemap of the coins sized by market cap
+    Heatmap,
+}
+
+/// Which part of the screen is shown on terminals too small for everything at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactTab {
+    Coins,
+    Details,
+}
+
+/// Where the price chart gets its data from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartSource {
+    /// CoinGecko's 7 day sparkline (about one point per hour)
+    Sparkline,
+    /// Snapshots stored by the local recorder, one point per refresh
+    Recorded,
 }
 
 pub struct App {
     pub coins: Vec<CoinMarket>,
+    /// Columns of the coin table, in display order
+    pub columns: Vec<Column>,
+    pub sparkline: SparklineStyle,
+    /// Field and direction (`true` for descending) the coins are sorted by, `None` for rank
+    pub sort: Option<(Field, bool)>,
     pub selected_index: usize,
     pub scroll_offset: usize,
+    /// Rows and headers of the coin table as last drawn
+    pub table: CoinTableState,
+    pub view: View,
+    pub compact_tab: CompactTab,
+    /// Coin rows that fit in the table, as of the last draw
+    pub visible_rows: usize,
+    pub heatmap: HeatmapState,
     pub chart_timeframe: ChartTimeframe,
+    pub chart_source: ChartSource,
     pub chart_data: Vec<f64>,
+    pub chart_times: Vec<DateTime<Utc>>,
+    /// Where the chart and its title were last drawn
+    pub chart_area: Option<Rect>,
+    pub chart_title_area: Option<Rect>,
+    /// Chart point under the mouse
+    pub chart_hover: Option<usize>,
     pub last_update: Option<Instant>,
+    pub stale_since: Option<DateTime<Utc>>,
+    pub offline: bool,
     pub loading: bool,
-    pub error_message: Option<String>,
+    pub error: Option<ApiError>,
     pub should_quit: bool,
     pub vs_currency: String,
+    pub api_plan: ApiPlan,
+    pub rate_limit: RateLimitStatus,
+    pub recorder: Option<Recorder>,
+    pub recorder_error: Option<String>,
+    pub export_dir: PathBuf,
+    pub export_dialog: Option<ExportDialog>,
+    pub converter: Option<ConverterDialog>,
+    pub palette: Option<CommandPalette>,
+    pub fiat_rates: FiatRates,
+    pub toast: Option<Toast>,
+    pub keymap: Keymap,
+    /// Keys typed so far of a multi-key binding
+    pub pending_keys: Vec<KeyChord>,
+    /// Whether the key binding overlay is open
+    pub show_help: bool,
+    pub themes: Vec<Theme>,
+    pub theme_index: usize,
+    /// Custom layouts from the config file
+    pub layouts: Vec<ScreenLayout>,
+    /// Custom layout in use, `None` for the built-in one
+    pub layout_index: Option<usize>,
+    pub color_depth: ColorDepth,
+    pub change_colors: ChangeColors,
+    pub change_signs: bool,
+    /// Colors of the current theme, fitted to the color depth
+    pub style: WidgetStyle,
 }
 
 impl App {
     pub fn new() -> Self {
         Self {
             coins: Vec::new(),
+            columns: Column::DEFAULT.to_vec(),
+            sparkline: SparklineStyle::Blocks,
+            sort: None,
             selected_index: 0,
             scroll_offset: 0,
+            table: CoinTableState::default(),
+            view: View::Table,
+            compact_tab: CompactTab::Coins,
+            visible_rows: 10,
+            heatmap: HeatmapState::default(),
             chart_timeframe: ChartTimeframe::TwentyFourHours,
+            chart_source: ChartSource::Sparkline,
             chart_data: Vec::new(),
+            chart_times: Vec::new(),
+            chart_area: None,
+            chart_title_area: None,
+            chart_hover: None,
             last_update: None,
+            stale_since: None,
+            offline: false,
             loading: true,
-            error_message: None,
+            error: None,
             should_quit: false,
             vs_currency: "usd".to_string(),
+            api_plan: ApiPlan::Public,
+            rate_limit: RateLimitStatus::default(),
+            recorder: None,
+            recorder_error: None,
+            export_dir: PathBuf::from("."),
+            export_dialog: None,
+            converter: None,
+            palette: None,
+            fiat_rates: FiatRates::base_only("usd"),
+            toast: None,
+            keymap: Keymap::default(),
+            pending_keys: Vec::new(),
+            show_help: false,
+            themes: Theme::builtin(),
+            theme_index: 0,
+            layouts: Vec::new(),
+            layout_index: None,
+            color_depth: ColorDepth::TrueColor,
+            change_colors: ChangeColors::Standard,
+            change_signs: false,
+            style: WidgetStyle::DEFAULT,
         }
     }
 
+    /// Fields shown in the coin table, also used when exporting it
+    pub fn table_fields(&self) -> Vec<Field> {
+        self.columns.iter().filter_map(Column::field).collect()
+    }
+
+    pub fn show_toast(&mut self, message: String, is_error: bool) {
+        self.toast = Some(Toast {
+            message,
+            is_error,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Action bound to the keys typed so far, waiting for more when they start a longer binding
+    pub fn key_action(&mut self, key: KeyEvent) -> Option<Action> {
+        self.pending_keys.push(KeyChord::from(key));
+        match self.keymap.lookup(&self.pending_keys) {
+            Lookup::Pending => None,
+            Lookup::Action(action) => {
+                self.pending_keys.clear();
+                Some(action)
+            }
+            // A sequence that went nowhere, the last key may still start a binding of its own
+            Lookup::None if self.pending_keys.len() > 1 => {
+                self.pending_keys.clear();
+                self.key_action(key)
+            }
+            Lookup::None => {
+                self.pending_keys.clear();
+                None
+            }
+        }
+    }
+
+    /// Switch to the theme at `index` in `themes`
+    pub fn set_theme(&mut self, index: usize) {
+        self.theme_index = index % self.themes.len();
+        self.style = self.themes[self.theme_index].style_for(self.color_depth, self.change_colors);
+        self.style.change_signs = self.change_signs;
+    }
+
+    pub fn cycle_theme(&mut self) {
+        self.set_theme(self.theme_index + 1);
+        let name = self.themes[self.theme_index].name.clone();
+        self.show_toast(format!("Theme: {}", name), false);
+    }
+
+    /// Custom layout in use, if any
+    pub fn layout(&self) -> Option<&ScreenLayout> {
+        self.layout_index.map(|i| &self.layouts[i])
+    }
+
+    /// Step through the custom layouts and back to the built-in one
+    pub fn cycle_layout(&mut self) {
+        if self.layouts.is_empty() {
+            self.show_toast("No layouts in the config file".to_string(), true);
+            return;
+        }
+        self.layout_index = match self.layout_index {
+            None => Some(0),
+            Some(i) if i + 1 < self.layouts.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        let name = self.layout().map_or("default", |l| l.name.as_str()).to_string();
+        self.show_toast(format!("Layout: {}", name), false);
+    }
+
+    /// Open the converter from the selected coin into the quote currency
+    pub fn open_converter(&mut self) {
+        let from = self.selected_coin().map(|c| c.symbol.clone()).unwrap_or_default();
+        self.converter = Some(ConverterDialog::new(&from, &self.vs_currency));
+    }
+
+    /// Resolve a converter field against the loaded coins and exchange rates
+    pub fn resolve_unit(&self, query: &str) -> Option<Unit> {
+        convert::resolve_unit(query, &self.coins, &self.fiat_rates)
+    }
+
     pub fn selected_coin(&self) -> Option<&CoinMarket> {
         self.coins.get(self.selected_index)
     }
@@ -90,6 +289,104 @@ impl App {
         self.selected_index = self.coins.len().saturating_sub(1);
     }
 
+    pub fn toggle_view(&mut self) {
+        self.view = match self.view {
+            View::Table => View::Heatmap,
+            View::Heatmap => View::Table,
+        };
+    }
+
+    pub fn toggle_compact_tab(&mut self) {
+        self.compact_tab = match self.compact_tab {
+            CompactTab::Coins => CompactTab::Details,
+            CompactTab::Details => CompactTab::Coins,
+        };
+    }
+
+    /// Select the heatmap tile next to the selected one, see [`HeatmapState::neighbor`]
+    pub fn move_heatmap_selection(&mut self, dx: i32, dy: i32) {
+        if let Some(index) = self.heatmap.neighbor(dx, dy) {
+            self.selected_index = index;
+        }
+    }
+
+    /// Sort by `field` in its natural direction, then the other way, then back to rank
+    pub fn sort_by(&mut self, field: Field) {
+        let natural = field.default_descending();
+        let sort = match self.sort {
+            Some((current, descending)) if current == field && descending == natural => Some((field, !natural)),
+            Some((current, _)) if current == field => None,
+            _ => Some((field, natural)),
+        };
+        self.set_sort(sort);
+    }
+
+    /// Sort by `field` in the given direction, or by rank for `None`
+    pub fn set_sort(&mut self, sort: Option<(Field, bool)>) {
+        self.sort = sort;
+        let selected_id = self.selected_coin().map(|coin| coin.id.clone());
+        self.reorder(selected_id);
+    }
+
+    /// Replace the coins with freshly fetched ones, keeping the sort order and the selected coin
+    pub fn set_coins(&mut self, coins: Vec<CoinMarket>) {
+        let selected_id = self.selected_coin().map(|coin| coin.id.clone());
+        self.coins = coins;
+        if self.sort.is_some() {
+            self.reorder(selected_id);
+        }
+    }
+
+    /// Put the coins in the order of `sort` and select the coin with `selected_id` again
+    fn reorder(&mut self, selected_id: Option<String>) {
+        let (field, descending) = self.sort.unwrap_or((Field::Rank, false));
+        sort_coins(&mut self.coins, field, descending);
+        if let Some(index) = selected_id.and_then(|id| self.coins.iter().position(|coin| coin.id == id)) {
+            self.selected_index = index;
+        }
+    }
+
+    /// React to a click, scroll or mouse move over what the last frame drew
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let (x, y) = (mouse.column, mouse.row);
+        let over = |area: Option<Rect>| area.is_some_and(|area| area.contains(Position::new(x, y)));
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) if over(self.chart_title_area) => {
+                self.cycle_timeframe();
+                self.update_chart_data();
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(column) = self.table.header_at(x, y) {
+                    if let Some(field) = column.field() {
+                        self.sort_by(field);
+                    }
+                    return;
+                }
+                let tile = self.heatmap.tiles().iter().find(|(_, tile)| tile.contains(Position::new(x, y)));
+                match self.table.coin_at(x, y).or(tile.map(|(i, _)| *i)) {
+                    Some(index) if index < self.coins.len() => self.selected_index = index,
+                    _ => self.chart_hover = self.chart_index_at(x, y),
+                }
+            }
+            MouseEventKind::Moved => {
+                self.chart_hover = self.chart_index_at(x, y);
+            }
+            MouseEventKind::ScrollUp if over(Some(self.table.area())) => {
+                self.move_selection_up(self.visible_rows);
+            }
+            MouseEventKind::ScrollDown if over(Some(self.table.area())) => {
+                self.move_selection_down(self.visible_rows);
+            }
+            _ => {}
+        }
+    }
+
+    /// Index into `chart_data` of the point drawn at `x`/`y`
+    fn chart_index_at(&self, x: u16, y: u16) -> Option<usize> {
+        let area = self.chart_area.filter(|area| area.contains(Position::new(x, y)))?;
+        PriceChart::new(&self.chart_data).index_at(area, x)
+    }
+
     pub fn cycle_timeframe(&mut self) {
         self.chart_timeframe = match self.chart_timeframe {
             ChartTimeframe::OneHour => ChartTimeframe::TwentyFourHours,
@@ -98,30 +395,51 @@ impl App {
         };
     }
 
+    pub fn toggle_chart_source(&mut self) {
+        self.chart_source = match self.chart_source {
+            ChartSource::Sparkline if self.recorder.is_some() => ChartSource::Recorded,
+            _ => ChartSource::Sparkline,
+        };
+    }
+
+    /// Store the current coins in the history database, if recording
+    pub fn record_snapshot(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.record(&self.vs_currency, &self.coins) {
+                self.recorder_error = Some(format!("History recording failed: {:#}", e));
+            }
+        }
+    }
+
     pub fn update_chart_data(&mut self) {
-        if let Some(coin) = self.selected_coin() {
-            if let Some(sparkline) = &coin.sparkline_in_7d {
-                let prices = &sparkline.price;
-                let len = prices.len();
-                
-                self.chart_data = match self.chart_timeframe {
-                    ChartTimeframe::OneHour => {
-                        // Last ~4 data points (7 days / 168 hours * 1 hour)
-                        let points = (len / 168).max(1);
-                        prices.iter().rev().take(points * 4).rev().cloned().collect()
-                    }
-                    ChartTimeframe::TwentyFourHours => {
-                        // Last ~24 data points
-                        let points = (len / 7).max(1);
-                        prices.iter().rev().take(points).rev().cloned().collect()
-                    }
-                    ChartTimeframe::SevenDays => {
-                        // All data points
-                        prices.clone()
-                    }
-                };
-            } else {
+        if self.chart_source == ChartSource::Recorded {
+            self.update_recorded_chart_data();
+            return;
+        }
+
+        // No coin, e.g. after switching to a currency with nothing cached yet
+        (self.chart_times, self.chart_data) = match self.selected_coin() {
+            Some(coin) => sparkline_series(coin, self.chart_timeframe).into_iter().unzip(),
+            None => Default::default(),
+        };
+    }
+
+    fn update_recorded_chart_data(&mut self) {
+        let (Some(recorder), Some(coin)) = (&self.recorder, self.selected_coin()) else {
+            self.chart_data.clear();
+            self.chart_times.clear();
+            return;
+        };
+
+        let since = Utc::now() - self.chart_timeframe.duration();
+        match recorder.price_history(&coin.id, &self.vs_currency, since) {
+            Ok(history) => {
+                (self.chart_times, self.chart_data) = history.into_iter().unzip();
+            }
+            Err(e) => {
                 self.chart_data.clear();
+                self.chart_times.clear();
+                self.recorder_error = Some(format!("Failed to read history: {:#}", e));
             }
         }
     }
@@ -140,3 +458,27 @@ impl Default for App {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chart_clears_without_a_selected_coin() {
+        let mut app = App::new();
+        app.coins = serde_json::from_str(
+            r#"[{"id": "bitcoin", "symbol": "btc", "name": "Bitcoin",
+                 "sparkline_in_7d": {"price": [1.0, 2.0, 3.0]}}]"#,
+        )
+        .unwrap();
+        app.chart_timeframe = ChartTimeframe::SevenDays;
+        app.update_chart_data();
+        assert_eq!(app.chart_data, [1.0, 2.0, 3.0]);
+        assert_eq!(app.chart_times.len(), 3);
+
+        app.coins.clear();
+        app.update_chart_data();
+        assert!(app.chart_data.is_empty());
+        assert!(app.chart_times.is_empty());
+    }
+}
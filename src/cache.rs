@@ -0,0 +1,134 @@
+//! On-disk cache of raw API responses
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+/// A cached value together with the time it was fetched
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    /// The cached value
+    pub data: T,
+    /// When the response was stored
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<'a> {
+    fetched_at: DateTime<Utc>,
+    #[serde(borrow)]
+    body: &'a RawValue,
+}
+
+/// On-disk store of raw API responses, keyed by request path
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Cache stored in `dir`, created on the first write
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Cache under the platform cache directory, e.g. `~/.cache/coins`
+    pub fn open_default() -> Option<Self> {
+        dirs::cache_dir().map(|dir| Self::new(dir.join("coins")))
+    }
+
+    /// Read the raw JSON body stored for `key`
+    pub fn get(&self, key: &str) -> Option<Cached<Vec<u8>>> {
+        let content = fs::read(self.path(key)).ok()?;
+        let entry: Entry = serde_json::from_slice(&content).ok()?;
+        Some(Cached {
+            data: entry.body.get().as_bytes().to_vec(),
+            fetched_at: entry.fetched_at,
+        })
+    }
+
+    /// Store a raw JSON body for `key`, replacing the previous entry atomically
+    pub fn put(&self, key: &str, body: &[u8]) -> std::io::Result<()> {
+        let body = std::str::from_utf8(body)
+            .ok()
+            .and_then(|b| serde_json::from_str::<&RawValue>(b).ok())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "response is not JSON"))?;
+        let entry = Entry {
+            fetched_at: Utc::now(),
+            body,
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        // The TUI, `coins bar` and `coins serve` may share the directory, so every write gets
+        // its own temporary file and only complete entries are renamed into place
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let tmp = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written = fs::File::create_new(&tmp).and_then(|mut file| {
+            file.write_all(&serde_json::to_vec(&entry)?)?;
+            file.sync_all()
+        });
+        match written.and_then(|()| fs::rename(&tmp, path)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                Err(e)
+            }
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // Readable prefix from the endpoint plus a stable hash of the full key
+        let prefix: String = key
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}-{:016x}.json", prefix.trim_matches('_'), fnv1a(key)))
+    }
+}
+
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_writers_leave_a_complete_entry() {
+        let dir = std::env::temp_dir().join(format!("coins-cache-test-{}", std::process::id()));
+        let cache = Cache::new(dir.clone());
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let cache = &cache;
+                s.spawn(move || {
+                    for _ in 0..20 {
+                        cache.put("/coins/markets?vs_currency=usd", format!("[{}]", i).as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let cached = cache.get("/coins/markets?vs_currency=usd").unwrap();
+        let value: Vec<u32> = serde_json::from_slice(&cached.data).unwrap();
+        assert!(value.len() == 1 && value[0] < 8);
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1, "temporary files left behind");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+//! Price chart data derived from market responses
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::api::CoinMarket;
+
+/// Span of price history shown in a chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartTimeframe {
+    /// The last hour
+    OneHour,
+    /// The last 24 hours
+    TwentyFourHours,
+    /// The last 7 days
+    SevenDays,
+}
+
+impl ChartTimeframe {
+    /// Short label, e.g. `24H`
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChartTimeframe::OneHour => "1H",
+            ChartTimeframe::TwentyFourHours => "24H",
+            ChartTimeframe::SevenDays => "7D",
+        }
+    }
+
+    /// Parse a label such as `24h`, ignoring case
+    pub fn parse(s: &str) -> Option<Self> {
+        [Self::OneHour, Self::TwentyFourHours, Self::SevenDays]
+            .into_iter()
+            .find(|tf| tf.label().eq_ignore_ascii_case(s))
+    }
+
+    /// The coin's price change over the timeframe, in percent
+    pub fn change(&self, coin: &CoinMarket) -> Option<f64> {
+        match self {
+            ChartTimeframe::OneHour => coin.price_change_percentage_1h_in_currency,
+            ChartTimeframe::TwentyFourHours => coin.price_change_percentage_24h_in_currency,
+            ChartTimeframe::SevenDays => coin.price_change_percentage_7d_in_currency,
+        }
+    }
+
+    /// Length of the timeframe
+    pub fn duration(&self) -> Duration {
+        match self {
+            ChartTimeframe::OneHour => Duration::hours(1),
+            ChartTimeframe::TwentyFourHours => Duration::hours(24),
+            ChartTimeframe::SevenDays => Duration::days(7),
+        }
+    }
+}
+
+/// Timestamped tail of a coin's 7 day sparkline covering `timeframe`
+pub fn sparkline_series(coin: &CoinMarket, timeframe: ChartTimeframe) -> Vec<(DateTime<Utc>, f64)> {
+    let Some(sparkline) = &coin.sparkline_in_7d else {
+        return Vec::new();
+    };
+    let prices = &sparkline.price;
+    let len = prices.len();
+    // Sparkline points are evenly spaced over 7 days, ending at the last update
+    let end = coin
+        .last_updated
+        .as_deref()
+        .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+        .map_or_else(Utc::now, |d| d.with_timezone(&Utc));
+
+    let count = match timeframe {
+        // Last ~4 data points (7 days / 168 hours * 1 hour)
+        ChartTimeframe::OneHour => (len / 168).max(1) * 4,
+        // Last ~24 data points
+        ChartTimeframe::TwentyFourHours => (len / 7).max(1),
+        // All data points
+        ChartTimeframe::SevenDays => len,
+    }
+    .min(len);
+
+    let step = Duration::days(7) / (len.max(2) - 1) as i32;
+    prices[len - count..]
+        .iter()
+        .enumerate()
+        .map(|(i, price)| (end - step * (count - 1 - i) as i32, *price))
+        .collect()
+}
+
+/// Resample `prices` to `width` points by averaging evenly sized buckets
+pub fn resample(prices: &[f64], width: usize) -> Vec<f64> {
+    if prices.is_empty() || width == 0 {
+        return Vec::new();
+    }
+    (0..width)
+        .map(|i| {
+            let start = i * prices.len() / width;
+            let end = ((i + 1) * prices.len() / width).max(start + 1).min(prices.len());
+            let bucket = &prices[start.min(end - 1)..end];
+            bucket.iter().sum::<f64>() / bucket.len() as f64
+        })
+        .collect()
+}
+
+/// One-line sparkline of `prices` using block characters, `width` characters wide
+pub fn sparkline_blocks(prices: &[f64], width: usize) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let points = resample(prices, width);
+    let min = points.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = points.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    points
+        .iter()
+        .map(|p| {
+            let level = if range > 0.0 { ((p - min) / range * 7.0).round() as usize } else { 3 };
+            BLOCKS[level.min(7)]
+        })
+        .collect()
+}
+
+/// One-line sparkline of `prices` using Braille dots, `width` characters wide.
+/// Each character holds two points and four levels, with consecutive points joined up.
+pub fn sparkline_braille(prices: &[f64], width: usize) -> String {
+    // Dot bits of the left and right column of a Braille cell, from the bottom row up
+    const LEFT: [u32; 4] = [0x40, 0x04, 0x02, 0x01];
+    const RIGHT: [u32; 4] = [0x80, 0x20, 0x10, 0x08];
+
+    let points = resample(prices, width * 2);
+    let min = points.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = points.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    let levels: Vec<usize> = points
+        .iter()
+        .map(|p| if range > 0.0 { ((p - min) / range * 3.0).round() as usize } else { 1 })
+        .collect();
+
+    let mut cells = vec![0u32; width];
+    for (i, &level) in levels.iter().enumerate() {
+        let previous = if i > 0 { levels[i - 1] } else { level };
+        let dots = if i % 2 == 0 { &LEFT } else { &RIGHT };
+        for row in level.min(previous)..=level.max(previous) {
+            cells[i / 2] |= dots[row.min(3)];
+        }
+    }
+    cells
+        .into_iter()
+        .map(|bits| char::from_u32(0x2800 + bits).unwrap_or(' '))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_averages_buckets() {
+        assert_eq!(resample(&[1.0, 3.0, 5.0, 7.0], 2), [2.0, 6.0]);
+        assert_eq!(resample(&[1.0, 2.0, 3.0], 3), [1.0, 2.0, 3.0]);
+        // Fewer points than the width repeats them
+        assert_eq!(resample(&[1.0, 2.0], 4), [1.0, 1.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn empty_series() {
+        assert!(resample(&[], 4).is_empty());
+        assert!(resample(&[1.0], 0).is_empty());
+        assert_eq!(sparkline_blocks(&[], 4), "");
+        assert_eq!(sparkline_braille(&[], 3), "\u{2800}\u{2800}\u{2800}");
+        assert_eq!(sparkline_braille(&[1.0, 2.0], 0), "");
+    }
+
+    #[test]
+    fn single_point_and_flat_series_sit_mid_height() {
+        assert_eq!(resample(&[5.0], 3), [5.0, 5.0, 5.0]);
+        assert_eq!(sparkline_blocks(&[5.0], 3), "▄▄▄");
+        assert_eq!(sparkline_blocks(&[2.0; 10], 4), "▄▄▄▄");
+        assert_eq!(sparkline_braille(&[5.0], 2), "⠤⠤");
+        assert_eq!(sparkline_braille(&[2.0; 10], 2), "⠤⠤");
+    }
+
+    #[test]
+    fn sparklines_follow_the_trend() {
+        assert_eq!(sparkline_blocks(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0], 8), "▁▂▃▄▅▆▇█");
+        // Rising from the bottom left dot to the top right one, joining the levels in between
+        assert_eq!(sparkline_braille(&[0.0, 1.0, 2.0, 3.0], 2), "⣠⠞");
+    }
+}
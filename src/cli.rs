@@ -0,0 +1,631 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, Utc};
+
+use thiserror::Error;
+use tokio::runtime::Runtime;
+
+use crate::api::{find_coin, ApiError, CoinGeckoClient, CoinMarket};
+use crate::config::Config;
+use crate::convert::{self, FiatRates};
+use crate::field::{sort_coins, Field};
+use crate::format::{format_amount, format_price};
+use crate::output::{json_string, write_coin, write_coins, OutputFormat};
+use crate::serve::{self, ServeConfig};
+use crate::template::Template;
+
+/// Subcommands that print to stdout instead of launching the TUI
+pub const COMMANDS: [&str; 7] = ["price", "list", "info", "convert", "watch", "bar", "serve"];
+
+const PRICE_FIELDS: [Field; 5] = [
+    Field::Symbol,
+    Field::Name,
+    Field::Price,
+    Field::Change24h,
+    Field::Change7d,
+];
+
+const LIST_FIELDS: [Field; 8] = [
+    Field::Rank,
+    Field::Symbol,
+    Field::Name,
+    Field::Price,
+    Field::Change1h,
+    Field::Change24h,
+    Field::Change7d,
+    Field::MarketCap,
+];
+
+const WATCH_FIELDS: [Field; 3] = [Field::Symbol, Field::Price, Field::Change24h];
+
+const DEFAULT_ADDR: &str = "127.0.0.1:9100";
+
+const BAR_FORMAT: &str = "{symbol} {price} {change_24h}";
+
+const INFO_FIELDS: [Field; 25] = [
+    Field::Rank,
+    Field::Id,
+    Field::Price,
+    Field::High24h,
+    Field::Low24h,
+    Field::Change1h,
+    Field::Change24h,
+    Field::Change7d,
+    Field::Change14d,
+    Field::Change30d,
+    Field::Change60d,
+    Field::Change200d,
+    Field::Change1y,
+    Field::MarketCap,
+    Field::Fdv,
+    Field::Volume,
+    Field::CirculatingSupply,
+    Field::TotalSupply,
+    Field::MaxSupply,
+    Field::Ath,
+    Field::AthChange,
+    Field::AthDate,
+    Field::Atl,
+    Field::AtlDate,
+    Field::LastUpdated,
+];
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("{0}")]
+    Usage(String),
+    #[error("coin not found: {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("{0:#}")]
+    Other(#[from] anyhow::Error),
+}
+
+/// Run a subcommand and return the process exit code:
+/// 0 on success, 1 if fetching or looking up data failed, 2 for invalid usage
+pub fn run(command: &str, args: &[String]) -> i32 {
+    let result = match command {
+        "price" => price(args),
+        "list" => list(args),
+        "info" => info(args),
+        "convert" => convert(args),
+        "watch" => watch(args),
+        "bar" => bar(args),
+        "serve" => serve(args),
+        _ => Err(CliError::Usage(format!("Unknown command: {}", command))),
+    };
+
+    match result {
+        Ok(()) => 0,
+        // The reader went away, e.g. `coins list | head`
+        Err(CliError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(CliError::Usage(msg)) => {
+            eprintln!("Error: {}", msg);
+            eprintln!("Use --help for usage information");
+            2
+        }
+        Err(CliError::Api(e)) => {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", e.hint());
+            1
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+/// `coins price btc eth --currency eur`
+fn price(args: &[String]) -> Result<(), CliError> {
+    let opts = Options::parse(args, &[CURRENCY, OUTPUT, FIELDS, OFFLINE])?;
+    if opts.positional.is_empty() {
+        return Err(CliError::Usage("price needs at least one coin".to_string()));
+    }
+    let (format, fields) = opts.output(OutputFormat::Table, &PRICE_FIELDS)?;
+
+    let session = Session::new(&opts)?;
+    let coins = session.resolve(&opts.positional)?;
+    let mut out = io::stdout().lock();
+    write_coins(&mut out, &coins, &fields, format, &session.vs_currency)?;
+    Ok(out.flush()?)
+}
+
+/// `coins list --top 20 --sort change24h`
+fn list(args: &[String]) -> Result<(), CliError> {
+    let opts = Options::parse(args, &[CURRENCY, TOP, SORT, ASC, DESC, OUTPUT, FIELDS, OFFLINE])?;
+    if let Some(arg) = opts.positional.first() {
+        return Err(CliError::Usage(format!("Unexpected argument: {}", arg)));
+    }
+
+    let top = opts.positive("top")?;
+    let sort = match opts.value("sort") {
+        Some(name) => Field::parse(name)
+            .ok_or_else(|| CliError::Usage(format!("Unknown sort field `{}`", name)))?,
+        None => Field::Rank,
+    };
+    let descending = match (opts.flag("asc"), opts.flag("desc")) {
+        (true, true) => return Err(CliError::Usage("--asc and --desc can't be combined".to_string())),
+        (true, false) => false,
+        (false, true) => true,
+        (false, false) => sort.default_descending(),
+    };
+    let (format, fields) = opts.output(OutputFormat::Table, &LIST_FIELDS)?;
+
+    let session = Session::new(&opts)?;
+    let mut coins = session.markets()?;
+    sort_coins(&mut coins, sort, descending);
+    if let Some(top) = top {
+        coins.truncate(top as usize);
+    }
+
+    let mut out = io::stdout().lock();
+    write_coins(&mut out, &coins, &fields, format, &session.vs_currency)?;
+    Ok(out.flush()?)
+}
+
+/// `coins info solana`
+fn info(args: &[String]) -> Result<(), CliError> {
+    let opts = Options::parse(args, &[CURRENCY, OUTPUT, FIELDS, OFFLINE])?;
+    let [query] = opts.positional.as_slice() else {
+        return Err(CliError::Usage("info needs exactly one coin".to_string()));
+    };
+    let (format, fields) = opts.output(OutputFormat::Table, &INFO_FIELDS)?;
+
+    let session = Session::new(&opts)?;
+    let coins = session.resolve(std::slice::from_ref(query))?;
+    let mut out = io::stdout().lock();
+    write_coin(&mut out, &coins[0], &fields, format, &session.vs_currency)?;
+    Ok(out.flush()?)
+}
+
+/// `coins convert 0.5 btc to eth`
+fn convert(args: &[String]) -> Result<(), CliError> {
+    let opts = Options::parse(args, &[OFFLINE])?;
+    let (amount, from, to) = match opts.positional.as_slice() {
+        [amount, from, to] => (amount, from, to),
+        [amount, from, word, to] if word.eq_ignore_ascii_case("to") || word.eq_ignore_ascii_case("in") => {
+            (amount, from, to)
+        }
+        _ => return Err(CliError::Usage("usage: coins convert <AMOUNT> <FROM> [to] <TO>".to_string())),
+    };
+    let amount = convert::parse_amount(amount)
+        .ok_or_else(|| CliError::Usage(format!("Invalid amount `{}`", amount)))?;
+
+    let session = Session::new(&opts)?;
+    let markets = session.markets()?;
+    // Currencies other than the quote currency need exchange rates
+    let rates = if find_coin(&markets, from).is_some() && find_coin(&markets, to).is_some() {
+        FiatRates::base_only(&session.vs_currency)
+    } else {
+        session
+            .rt
+            .block_on(convert::fetch_fiat_rates(&session.client, &session.vs_currency))?
+    };
+    let unit = |query: &str| {
+        convert::resolve_unit(query, &markets, &rates)
+            .ok_or_else(|| CliError::Other(anyhow::anyhow!("unknown coin or currency: {}", query)))
+    };
+    let (from, to) = (unit(from)?, unit(to)?);
+    let value = convert::convert(amount, &from, &to, &rates).ok_or_else(|| {
+        CliError::Other(anyhow::anyhow!("no rate available for {} to {}", from.code(), to.code()))
+    })?;
+
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        "{} {} = {} {}",
+        format_amount(amount),
+        from.code(),
+        format_amount(value),
+        to.code()
+    )?;
+    Ok(out.flush()?)
+}
+
+/// `coins watch btc eth sol --interval 30`
+fn watch(args: &[String]) -> Result<(), CliError> {
+    let opts = Options::parse(args, &[CURRENCY, INTERVAL, CROSS, OUTPUT, FIELDS, OFFLINE])?;
+    let thresholds = match opts.value("cross") {
+        Some(list) => parse_thresholds(list)?,
+        None => Vec::new(),
+    };
+    // Coins with a threshold are watched even when not listed
+    let mut queries = opts.positional.clone();
+    for threshold in &thresholds {
+        if !queries.iter().any(|q| q.eq_ignore_ascii_case(&threshold.query)) {
+            queries.push(threshold.query.clone());
+        }
+    }
+    if queries.is_empty() {
+        return Err(CliError::Usage("watch needs at least one coin".to_string()));
+    }
+    let (format, fields) = opts.output(OutputFormat::Line, &WATCH_FIELDS)?;
+
+    let session = Session::new(&opts)?;
+    let interval = match opts.positive("interval")? {
+        Some(secs) => Duration::from_secs(secs),
+        None => session.client.plan().refresh_interval(),
+    };
+
+    let currency = session.vs_currency.to_uppercase();
+    let mut previous: HashMap<String, f64> = HashMap::new();
+    let mut first = true;
+    loop {
+        let now = Local::now().format("%Y-%m-%d %H:%M:%S");
+        match session.resolve(&queries) {
+            Ok(coins) => {
+                let mut out = io::stdout().lock();
+                if thresholds.is_empty() {
+                    if format == OutputFormat::Line {
+                        write!(out, "{}  ", now)?;
+                    }
+                    write_coins(&mut out, &coins, &fields, format, &session.vs_currency)?;
+                }
+                for threshold in &thresholds {
+                    let Some(coin) = find_coin(&coins, &threshold.query) else {
+                        continue;
+                    };
+                    let (Some(before), Some(after)) = (previous.get(&coin.id).copied(), coin.current_price) else {
+                        continue;
+                    };
+                    let direction = if before < threshold.price && after >= threshold.price {
+                        "above"
+                    } else if before >= threshold.price && after < threshold.price {
+                        "below"
+                    } else {
+                        continue;
+                    };
+                    writeln!(
+                        out,
+                        "{}  {} crossed {} {} {} (now {})",
+                        now,
+                        coin.symbol.to_uppercase(),
+                        direction,
+                        format_price(threshold.price),
+                        currency,
+                        format_price(after)
+                    )?;
+                }
+                out.flush()?;
+                previous = coins
+                    .iter()
+                    .filter_map(|c| Some((c.id.clone(), c.current_price?)))
+                    .collect();
+            }
+            // Keep watching through failed refreshes once the first one succeeded
+            Err(CliError::Api(e)) if !first => eprintln!("{}  Error: {}", now, e),
+            Err(e) => return Err(e),
+        }
+        first = false;
+        thread::sleep(interval);
+    }
+}
+
+/// `coins bar btc eth --format "{symbol} {price:.0} {change_24h:+.1}%"`
+fn bar(args: &[String]) -> Result<(), CliError> {
+    let opts = Options::parse(args, &[CURRENCY, FORMAT, SEPARATOR, MAX_AGE, WAYBAR, OFFLINE])?;
+    if opts.positional.is_empty() {
+        return Err(CliError::Usage("bar needs at least one coin".to_string()));
+    }
+    let template = Template::parse(opts.value("format").unwrap_or(BAR_FORMAT)).map_err(CliError::Usage)?;
+
+    let mut session = Session::new(&opts)?;
+    // Status bars run this every few seconds, so serve recent data from the cache
+    session.max_age = Some(match opts.value("max-age") {
+        Some(secs) => Duration::from_secs(
+            secs.parse::<u64>()
+                .map_err(|_| CliError::Usage(format!("--max-age must be a number of seconds, got `{}`", secs)))?,
+        ),
+        None => session.client.plan().refresh_interval(),
+    });
+    let coins = session.resolve(&opts.positional)?;
+    let segments: Vec<String> = coins.iter().map(|coin| template.render(coin)).collect();
+    let text = segments.join(opts.value("separator").unwrap_or("  "));
+
+    let mut out = io::stdout().lock();
+    if opts.flag("waybar") {
+        let tooltip: Vec<String> = coins
+            .iter()
+            .map(|coin| {
+                format!(
+                    "{} {} {} ({})",
+                    coin.name,
+                    Field::Price.display(coin),
+                    session.vs_currency.to_uppercase(),
+                    Field::Change24h.display(coin)
+                )
+            })
+            .collect();
+        let changes: Vec<f64> = coins.iter().filter_map(|c| c.price_change_percentage_24h_in_currency).collect();
+        let class = if changes.is_empty() {
+            "unknown"
+        } else if changes.iter().all(|c| *c >= 0.0) {
+            "up"
+        } else if changes.iter().all(|c| *c < 0.0) {
+            "down"
+        } else {
+            "mixed"
+        };
+        writeln!(
+            out,
+            "{{\"text\":{},\"tooltip\":{},\"class\":{}}}",
+            json_string(&text),
+            json_string(&tooltip.join("\n")),
+            json_string(class)
+        )?;
+    } else {
+        writeln!(out, "{}", text)?;
+    }
+    Ok(out.flush()?)
+}
+
+/// `coins serve --addr 127.0.0.1:9100 --currency usd,eur`
+fn serve(args: &[String]) -> Result<(), CliError> {
+    let opts = Options::parse(args, &[CURRENCY, TOP, INTERVAL, ADDR, METRICS_ADDR])?;
+    if let Some(arg) = opts.positional.first() {
+        return Err(CliError::Usage(format!("Unexpected argument: {}", arg)));
+    }
+    let top = opts.positive("top")?;
+    let session = Session::new(&opts)?;
+    let config = ServeConfig {
+        currencies: session
+            .vs_currency
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(String::from)
+            .collect(),
+        top: top.map(|n| n as usize),
+        interval: match opts.positive("interval")? {
+            Some(secs) => Duration::from_secs(secs),
+            None => session.client.plan().refresh_interval(),
+        },
+        addr: opts
+            .value("addr")
+            .or(opts.value("metrics-addr"))
+            .unwrap_or(DEFAULT_ADDR)
+            .to_string(),
+    };
+
+    if config.currencies.is_empty() {
+        return Err(CliError::Usage("--currency needs at least one currency".to_string()));
+    }
+
+    Ok(session.rt.block_on(serve::run(session.client.clone(), config))?)
+}
+
+/// `--cross btc=70000`: report when a coin's price moves across `price`
+struct Threshold {
+    query: String,
+    price: f64,
+}
+
+/// Parse a comma-separated list of `COIN=PRICE` thresholds
+fn parse_thresholds(list: &str) -> Result<Vec<Threshold>, CliError> {
+    list.split(',')
+        .map(|item| {
+            let (query, price) = item
+                .split_once('=')
+                .filter(|(query, _)| !query.trim().is_empty())
+                .ok_or_else(|| CliError::Usage(format!("--cross expects COIN=PRICE, got `{}`", item)))?;
+            let price = price
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|p| p.is_finite() && *p > 0.0)
+                .ok_or_else(|| CliError::Usage(format!("Invalid price in --cross: `{}`", item)))?;
+            Ok(Threshold { query: query.trim().to_string(), price })
+        })
+        .collect()
+}
+
+/// Runtime, client and currency shared by the subcommands
+struct Session {
+    rt: Runtime,
+    client: CoinGeckoClient,
+    vs_currency: String,
+    offline: bool,
+    /// Serve markets from the cache when it is at most this old
+    max_age: Option<Duration>,
+}
+
+impl Session {
+    fn new(opts: &Options) -> Result<Self, CliError> {
+        let offline = opts.flag("offline");
+        let config = Config::load()?;
+        let client = config.client(offline)?;
+        let rt = Runtime::new().map_err(anyhow::Error::from)?;
+        let vs_currency = opts.value("currency").or(config.currency.as_deref()).unwrap_or("usd");
+
+        Ok(Self {
+            rt,
+            client,
+            vs_currency: vs_currency.to_lowercase(),
+            offline,
+            max_age: None,
+        })
+    }
+
+    fn markets(&self) -> Result<Vec<CoinMarket>, CliError> {
+        let Some(max_age) = self.max_age else {
+            return Ok(self.rt.block_on(self.client.get_markets(&self.vs_currency))?);
+        };
+
+        let cached = self.client.cached_markets(&self.vs_currency);
+        let fresh = cached.as_ref().is_some_and(|cached| {
+            (Utc::now() - cached.fetched_at).to_std().is_ok_and(|age| age <= max_age)
+        });
+        match cached {
+            Some(cached) if fresh => Ok(cached.data),
+            // Stale data beats no data when the refresh fails
+            cached => match self.rt.block_on(self.client.get_markets(&self.vs_currency)) {
+                Ok(coins) => Ok(coins),
+                Err(e) => cached.map(|cached| cached.data).ok_or(CliError::Api(e)),
+            },
+        }
+    }
+
+    /// Look up coins by id, symbol or name, keeping the order of `queries`.
+    /// Coins outside the top 100 are fetched by id.
+    fn resolve(&self, queries: &[String]) -> Result<Vec<CoinMarket>, CliError> {
+        let markets = self.markets()?;
+        let mut found: Vec<Option<CoinMarket>> = queries
+            .iter()
+            .map(|query| find_coin(&markets, query).cloned())
+            .collect();
+
+        let missing: Vec<String> = queries
+            .iter()
+            .zip(&found)
+            .filter(|(query, coin)| coin.is_none() && is_coin_id(query))
+            .map(|(query, _)| query.to_lowercase())
+            .collect();
+        if !missing.is_empty() && !self.offline {
+            let extra = self
+                .rt
+                .block_on(self.client.get_markets_by_ids(&self.vs_currency, &missing))?;
+            for (query, slot) in queries.iter().zip(found.iter_mut()) {
+                if slot.is_none() {
+                    *slot = extra.iter().find(|c| c.id.eq_ignore_ascii_case(query)).cloned();
+                }
+            }
+        }
+
+        let not_found: Vec<&str> = queries
+            .iter()
+            .zip(&found)
+            .filter(|(_, coin)| coin.is_none())
+            .map(|(query, _)| query.as_str())
+            .collect();
+        if !not_found.is_empty() {
+            return Err(CliError::NotFound(not_found.join(", ")));
+        }
+
+        Ok(found.into_iter().flatten().collect())
+    }
+}
+
+/// CoinGecko ids are lowercase words joined by dashes, e.g. `wrapped-bitcoin`
+fn is_coin_id(query: &str) -> bool {
+    !query.is_empty()
+        && query
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Opt {
+    long: &'static str,
+    short: Option<char>,
+    takes_value: bool,
+}
+
+const CURRENCY: Opt = Opt { long: "currency", short: Some('c'), takes_value: true };
+const TOP: Opt = Opt { long: "top", short: Some('n'), takes_value: true };
+const SORT: Opt = Opt { long: "sort", short: Some('s'), takes_value: true };
+const ASC: Opt = Opt { long: "asc", short: None, takes_value: false };
+const DESC: Opt = Opt { long: "desc", short: None, takes_value: false };
+const OUTPUT: Opt = Opt { long: "output", short: Some('o'), takes_value: true };
+const FIELDS: Opt = Opt { long: "fields", short: Some('f'), takes_value: true };
+const OFFLINE: Opt = Opt { long: "offline", short: None, takes_value: false };
+const INTERVAL: Opt = Opt { long: "interval", short: Some('i'), takes_value: true };
+const CROSS: Opt = Opt { long: "cross", short: None, takes_value: true };
+const FORMAT: Opt = Opt { long: "format", short: None, takes_value: true };
+const SEPARATOR: Opt = Opt { long: "separator", short: None, takes_value: true };
+const MAX_AGE: Opt = Opt { long: "max-age", short: None, takes_value: true };
+const ADDR: Opt = Opt { long: "addr", short: None, takes_value: true };
+const METRICS_ADDR: Opt = Opt { long: "metrics-addr", short: None, takes_value: true };
+const WAYBAR: Opt = Opt { long: "waybar", short: None, takes_value: false };
+
+/// Parsed subcommand arguments
+#[derive(Debug, Default)]
+struct Options {
+    positional: Vec<String>,
+    values: HashMap<&'static str, String>,
+    flags: HashSet<&'static str>,
+}
+
+impl Options {
+    /// Parse `--name value`, `--name=value`, `-n value` and flags allowed by `spec`
+    fn parse(args: &[String], spec: &[Opt]) -> Result<Self, CliError> {
+        let mut opts = Options::default();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let (opt, inline) = if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                (spec.iter().find(|o| o.long == name), inline)
+            } else if let Some(short) = arg.strip_prefix('-').filter(|s| s.chars().count() == 1) {
+                let short = short.chars().next();
+                (spec.iter().find(|o| o.short == short), None)
+            } else {
+                opts.positional.push(arg.clone());
+                continue;
+            };
+
+            let opt = opt.ok_or_else(|| CliError::Usage(format!("Unknown option: {}", arg)))?;
+            if opt.takes_value {
+                let value = match inline {
+                    Some(value) => value,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", opt.long)))?,
+                };
+                opts.values.insert(opt.long, value);
+            } else if inline.is_some() {
+                return Err(CliError::Usage(format!("--{} doesn't take a value", opt.long)));
+            } else {
+                opts.flags.insert(opt.long);
+            }
+        }
+
+        Ok(opts)
+    }
+
+    /// `--output` format and `--fields` selection, falling back to the command's defaults
+    fn output(
+        &self,
+        default_format: OutputFormat,
+        default_fields: &[Field],
+    ) -> Result<(OutputFormat, Vec<Field>), CliError> {
+        let format = match self.value("output") {
+            Some(format) => format.parse().map_err(CliError::Usage)?,
+            None => default_format,
+        };
+        let fields = match self.value("fields") {
+            Some(fields) => Field::parse_list(fields).map_err(CliError::Usage)?,
+            None => default_fields.to_vec(),
+        };
+        Ok((format, fields))
+    }
+
+    /// Value of an option that must be a positive integer, e.g. `--top 20`
+    fn positive(&self, name: &str) -> Result<Option<u64>, CliError> {
+        let Some(value) = self.value(name) else {
+            return Ok(None);
+        };
+        match value.parse::<u64>() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(CliError::Usage(format!("--{} must be a positive number, got `{}`", name, value))),
+        }
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+}
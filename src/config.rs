@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::api::{ApiKey, ApiPlan, CoinGeckoClient};
+use crate::cache::Cache;
+use crate::keymap::KeysConfig;
+use crate::layout::LayoutConfig;
+use crate::recorder::RecorderConfig;
+use crate::theme::{ChangeColors, ColorDepth, ThemeConfig};
+use crate::widgets::{Column, SparklineStyle};
+
+const CONFIG_FILE: &str = "config.toml";
+const API_KEY_FILE: &str = "api_key";
+
+/// User configuration loaded from `~/.config/coins/config.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub api_key: Option<String>,
+    pub api_plan: Option<ApiPlan>,
+    pub recorder: RecorderConfig,
+    /// Currency to quote prices in, defaults to `usd`; `--currency` overrides it
+    pub currency: Option<String>,
+    /// Where `e` in the TUI writes exports, defaults to the current directory
+    pub export_dir: Option<PathBuf>,
+    /// Theme to start the TUI with, defaults to `dark`
+    pub theme: Option<String>,
+    /// Custom themes, keyed by name
+    pub themes: BTreeMap<String, ThemeConfig>,
+    /// Overrides the color depth detected from `NO_COLOR`, `COLORTERM` and `TERM`
+    pub color_depth: Option<ColorDepth>,
+    /// Colors of gains and losses: `standard`, `colorblind` or `red-up`
+    pub change_colors: ChangeColors,
+    /// Write changes as `▲ +1.23%` / `▼ −1.23%`, on by default in colorblind mode and without colors
+    pub change_signs: Option<bool>,
+    /// Coin table columns in display order, e.g. `["rank", "symbol", "price", "sparkline"]`
+    pub columns: Option<Vec<String>>,
+    /// How the `sparkline` column is drawn: `blocks` or `braille`
+    pub sparkline: SparklineStyle,
+    /// Custom screen layouts, switched between with `L`
+    pub layouts: Vec<LayoutConfig>,
+    /// Layout to start the TUI with, defaults to the built-in one that follows the terminal size
+    pub layout: Option<String>,
+    /// Keys for actions, replacing their default keys, e.g. `top = "g g"` or `quit = ["q", "ctrl-q"]`
+    pub keys: BTreeMap<String, KeysConfig>,
+}
+
+impl Config {
+    /// Directory holding the config file and the API key file
+    pub fn dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("coins"))
+    }
+
+    /// Load the config file, falling back to defaults when it doesn't exist
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::dir().map(|dir| dir.join(CONFIG_FILE)) else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Coin table columns, defaulting to rank, coin, price, 1h/24h/7d change and market cap
+    pub fn columns(&self) -> Result<Vec<Column>> {
+        match &self.columns {
+            Some(names) => Column::parse_list(&names.join(",")).map_err(anyhow::Error::msg),
+            None => Ok(Column::DEFAULT.to_vec()),
+        }
+    }
+
+    /// Build an API client using the configured key and the on-disk cache
+    pub fn client(&self, offline: bool) -> Result<CoinGeckoClient> {
+        Ok(CoinGeckoClient::with_api_key(self.resolve_api_key()?)
+            .with_cache(Cache::open_default())
+            .offline(offline))
+    }
+
+    /// Find an API key, checking in order:
+    /// `COINGECKO_PRO_API_KEY`, `COINGECKO_DEMO_API_KEY`, `COINGECKO_API_KEY`,
+    /// `api_key` in the config file, then the `api_key` file next to it.
+    /// The plan for a generic key comes from `COINGECKO_API_PLAN` or `api_plan`, defaulting to demo.
+    pub fn resolve_api_key(&self) -> Result<Option<ApiKey>> {
+        if let Some(key) = env_var("COINGECKO_PRO_API_KEY") {
+            return Ok(Some(ApiKey::new(ApiPlan::Pro, key)));
+        }
+        if let Some(key) = env_var("COINGECKO_DEMO_API_KEY") {
+            return Ok(Some(ApiKey::new(ApiPlan::Demo, key)));
+        }
+
+        let plan = match env_var("COINGECKO_API_PLAN") {
+            Some(plan) => plan.parse().map_err(anyhow::Error::msg)?,
+            None => self.api_plan.unwrap_or(ApiPlan::Demo),
+        };
+
+        let key = match env_var("COINGECKO_API_KEY").or_else(|| self.api_key.clone()) {
+            Some(key) => Some(key),
+            None => read_key_file()?,
+        };
+
+        match (plan, key) {
+            (ApiPlan::Public, _) | (_, None) => Ok(None),
+            (plan, Some(key)) => Ok(Some(ApiKey::new(plan, key))),
+        }
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Read the key from a private file so it doesn't have to live in the environment or config
+fn read_key_file() -> Result<Option<String>> {
+    let Some(path) = Config::dir().map(|dir| dir.join(API_KEY_FILE)) else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            eprintln!(
+                "Warning: {} is readable by other users, run `chmod 600` on it",
+                path.display()
+            );
+        }
+    }
+
+    let key = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let key = key.trim().to_string();
+    Ok((!key.is_empty()).then_some(key))
+}
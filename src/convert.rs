@@ -0,0 +1,122 @@
+//! Conversion between coins and fiat currencies at current prices
+
+use std::collections::HashMap;
+
+use crate::api::{self, find_coin, CoinGeckoClient, CoinMarket};
+
+/// Coin used to derive fiat exchange rates from `/simple/price`
+const RATE_PIVOT: &str = "bitcoin";
+
+/// Something an amount can be expressed in
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unit {
+    /// A coin with its price in the base currency
+    Coin {
+        /// Ticker symbol
+        symbol: String,
+        /// Display name
+        name: String,
+        /// Price of one coin in the base currency
+        price: f64,
+    },
+    /// A currency supported by CoinGecko as `vs_currency`
+    Fiat(String),
+}
+
+impl Unit {
+    /// Uppercase symbol or currency code, e.g. `BTC` or `EUR`
+    pub fn code(&self) -> String {
+        match self {
+            Unit::Coin { symbol, .. } => symbol.to_uppercase(),
+            Unit::Fiat(code) => code.to_uppercase(),
+        }
+    }
+
+    /// Coin name, or `Currency` for currencies
+    pub fn name(&self) -> &str {
+        match self {
+            Unit::Coin { name, .. } => name,
+            Unit::Fiat(_) => "Currency",
+        }
+    }
+}
+
+/// Exchange rates against the base currency that coin prices are quoted in
+#[derive(Debug, Clone)]
+pub struct FiatRates {
+    base: String,
+    /// Units of each currency per one unit of the base currency
+    per_base: HashMap<String, f64>,
+}
+
+impl FiatRates {
+    /// Rates that only know the base currency itself
+    pub fn base_only(base: &str) -> Self {
+        Self {
+            base: base.to_lowercase(),
+            per_base: HashMap::from([(base.to_lowercase(), 1.0)]),
+        }
+    }
+
+    /// Whether rates for currencies other than the base have been loaded
+    pub fn is_loaded(&self) -> bool {
+        self.per_base.len() > 1
+    }
+
+    /// Codes of the currencies there are rates for, in no particular order
+    pub fn currencies(&self) -> impl Iterator<Item = &str> {
+        self.per_base.keys().map(String::as_str)
+    }
+
+    fn value_in_base(&self, unit: &Unit) -> Option<f64> {
+        match unit {
+            Unit::Coin { price, .. } => Some(*price),
+            Unit::Fiat(code) => self.per_base.get(code).map(|rate| 1.0 / rate),
+        }
+    }
+}
+
+/// Load rates for every supported currency, using the pivot coin's price in each
+pub async fn fetch_fiat_rates(client: &CoinGeckoClient, base: &str) -> api::Result<FiatRates> {
+    let currencies = client.get_supported_vs_currencies().await?;
+    let prices = client
+        .get_simple_prices(&[RATE_PIVOT.to_string()], &currencies)
+        .await?;
+
+    let mut rates = FiatRates::base_only(base);
+    if let Some(quotes) = prices.get(RATE_PIVOT) {
+        if let Some(pivot_in_base) = quotes.get(&rates.base).copied().filter(|p| *p > 0.0) {
+            for (code, price) in quotes {
+                rates.per_base.insert(code.clone(), price / pivot_in_base);
+            }
+        }
+    }
+    Ok(rates)
+}
+
+/// Find a coin (by id, symbol or name) or a supported currency code; coins win on clashes
+pub fn resolve_unit(query: &str, coins: &[CoinMarket], rates: &FiatRates) -> Option<Unit> {
+    let query = query.trim();
+    if let Some(coin) = find_coin(coins, query) {
+        return Some(Unit::Coin {
+            symbol: coin.symbol.clone(),
+            name: coin.name.clone(),
+            price: coin.current_price?,
+        });
+    }
+
+    let code = query.to_lowercase();
+    rates.per_base.contains_key(&code).then_some(Unit::Fiat(code))
+}
+
+/// Convert `amount` of `from` into `to`
+pub fn convert(amount: f64, from: &Unit, to: &Unit, rates: &FiatRates) -> Option<f64> {
+    let from = rates.value_in_base(from)?;
+    let to = rates.value_in_base(to)?;
+    (to > 0.0).then(|| amount * from / to)
+}
+
+/// Parse an amount, accepting `,` as the decimal separator
+pub fn parse_amount(s: &str) -> Option<f64> {
+    s.trim().replace(',', ".").parse().ok().filter(|n: &f64| n.is_finite())
+}
@@ -0,0 +1,56 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::DialogAction;
+
+/// State of the converter popup
+#[derive(Debug, Clone)]
+pub struct ConverterDialog {
+    pub amount: String,
+    pub from: String,
+    pub to: String,
+    /// Focused row: 0 = amount, 1 = from, 2 = to
+    pub focus: usize,
+}
+
+impl ConverterDialog {
+    pub fn new(from: &str, to: &str) -> Self {
+        Self {
+            amount: "1".to_string(),
+            from: from.to_uppercase(),
+            to: to.to_uppercase(),
+            focus: 0,
+        }
+    }
+
+    pub fn swap(&mut self) {
+        std::mem::swap(&mut self.from, &mut self.to);
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> DialogAction {
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter => return DialogAction::Cancel,
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => self.swap(),
+            KeyCode::Up | KeyCode::BackTab => self.focus = (self.focus + 2) % 3,
+            KeyCode::Down | KeyCode::Tab => self.focus = (self.focus + 1) % 3,
+            KeyCode::Backspace => {
+                self.focused_field().pop();
+            }
+            KeyCode::Char(c) if self.focus == 0 && (c.is_ascii_digit() || c == '.' || c == ',') => {
+                self.amount.push(c)
+            }
+            KeyCode::Char(c) if self.focus > 0 && !c.is_whitespace() => {
+                self.focused_field().push(c.to_ascii_uppercase())
+            }
+            _ => {}
+        }
+        DialogAction::None
+    }
+
+    fn focused_field(&mut self) -> &mut String {
+        match self.focus {
+            0 => &mut self.amount,
+            1 => &mut self.from,
+            _ => &mut self.to,
+        }
+    }
+}
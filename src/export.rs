@@ -0,0 +1,141 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::{App, DialogAction};
+use crate::output::{write_coins, write_series, OutputFormat};
+
+/// What part of the screen to export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTarget {
+    Table,
+    Chart,
+}
+
+impl ExportTarget {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportTarget::Table => "Coin table",
+            ExportTarget::Chart => "Chart series",
+        }
+    }
+}
+
+pub const FORMATS: [OutputFormat; 3] = [OutputFormat::Csv, OutputFormat::Json, OutputFormat::Markdown];
+
+pub fn format_label(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Csv => "CSV",
+        OutputFormat::Json => "JSON",
+        OutputFormat::Markdown => "Markdown",
+        OutputFormat::Ndjson => "NDJSON",
+        OutputFormat::Table => "Text",
+        OutputFormat::Line => "Line",
+    }
+}
+
+/// State of the export popup
+#[derive(Debug, Clone)]
+pub struct ExportDialog {
+    pub target: ExportTarget,
+    pub format: OutputFormat,
+    pub directory: String,
+    /// Focused row: 0 = target, 1 = format, 2 = directory
+    pub focus: usize,
+}
+
+impl ExportDialog {
+    pub fn new(directory: &Path) -> Self {
+        Self {
+            target: ExportTarget::Table,
+            format: OutputFormat::Csv,
+            directory: directory.display().to_string(),
+            focus: 0,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> DialogAction {
+        match key.code {
+            KeyCode::Esc => return DialogAction::Cancel,
+            KeyCode::Enter => return DialogAction::Submit,
+            KeyCode::Up | KeyCode::BackTab => self.focus = (self.focus + 2) % 3,
+            KeyCode::Down | KeyCode::Tab => self.focus = (self.focus + 1) % 3,
+            KeyCode::Left | KeyCode::Right if self.focus == 0 => {
+                self.target = match self.target {
+                    ExportTarget::Table => ExportTarget::Chart,
+                    ExportTarget::Chart => ExportTarget::Table,
+                };
+            }
+            KeyCode::Left | KeyCode::Right if self.focus == 1 => {
+                let i = FORMATS.iter().position(|f| *f == self.format).unwrap_or(0);
+                let i = if key.code == KeyCode::Right {
+                    (i + 1) % FORMATS.len()
+                } else {
+                    (i + FORMATS.len() - 1) % FORMATS.len()
+                };
+                self.format = FORMATS[i];
+            }
+            KeyCode::Backspace if self.focus == 2 => {
+                self.directory.pop();
+            }
+            KeyCode::Char(c) if self.focus == 2 => self.directory.push(c),
+            _ => {}
+        }
+        DialogAction::None
+    }
+}
+
+/// Write the chosen view to a new timestamped file in the dialog's directory
+pub fn export(app: &App, dialog: &ExportDialog) -> Result<PathBuf> {
+    let dir = expand_home(dialog.directory.trim());
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let name = match dialog.target {
+        ExportTarget::Table => format!("coins-table-{}", stamp),
+        ExportTarget::Chart => {
+            let Some(coin) = app.selected_coin() else {
+                bail!("No coin selected");
+            };
+            format!(
+                "coins-{}-{}-{}",
+                coin.symbol.to_lowercase(),
+                app.chart_timeframe.label().to_lowercase(),
+                stamp
+            )
+        }
+    };
+    let path = dir.join(format!("{}.{}", name, dialog.format.extension()));
+
+    let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    match dialog.target {
+        ExportTarget::Table => {
+            write_coins(&mut out, &app.coins, &app.table_fields(), dialog.format, &app.vs_currency)?
+        }
+        ExportTarget::Chart => {
+            let points: Vec<_> = app
+                .chart_times
+                .iter()
+                .copied()
+                .zip(app.chart_data.iter().copied())
+                .collect();
+            write_series(&mut out, &points, dialog.format, &app.vs_currency)?
+        }
+    }
+    out.flush()?;
+
+    Ok(path)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/").zip(dirs::home_dir()) {
+        Some((rest, home)) => home.join(rest),
+        None if path.is_empty() => PathBuf::from("."),
+        None => PathBuf::from(path),
+    }
+}
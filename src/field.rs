@@ -0,0 +1,395 @@
+//! Named coin attributes shared by sorting, output formats and table columns
+
+use std::cmp::Ordering;
+
+use crate::api::CoinMarket;
+use crate::format::{format_change, format_large_number, format_price};
+
+/// A named attribute of `CoinMarket`, used for sorting, output fields and table columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Market cap rank (`rank`)
+    Rank,
+    /// CoinGecko id (`id`)
+    Id,
+    /// Ticker symbol (`symbol`)
+    Symbol,
+    /// Display name (`name`)
+    Name,
+    /// Current price (`price`)
+    Price,
+    /// Market capitalisation (`market_cap`)
+    MarketCap,
+    /// Fully diluted valuation (`fdv`)
+    Fdv,
+    /// 24 hour trading volume (`volume`)
+    Volume,
+    /// 24 hour high (`high_24h`)
+    High24h,
+    /// 24 hour low (`low_24h`)
+    Low24h,
+    /// 1 hour change in percent (`change_1h`)
+    Change1h,
+    /// 24 hour change in percent (`change_24h`)
+    Change24h,
+    /// 7 day change in percent (`change_7d`)
+    Change7d,
+    /// 14 day change in percent (`change_14d`)
+    Change14d,
+    /// 30 day change in percent (`change_30d`)
+    Change30d,
+    /// 60 day change in percent (`change_60d`)
+    Change60d,
+    /// 200 day change in percent (`change_200d`)
+    Change200d,
+    /// 1 year change in percent (`change_1y`)
+    Change1y,
+    /// Circulating supply (`circulating_supply`)
+    CirculatingSupply,
+    /// Total supply (`total_supply`)
+    TotalSupply,
+    /// Maximum supply (`max_supply`)
+    MaxSupply,
+    /// All-time high price (`ath`)
+    Ath,
+    /// Distance from the all-time high in percent (`ath_change`)
+    AthChange,
+    /// Date of the all-time high (`ath_date`)
+    AthDate,
+    /// All-time low price (`atl`)
+    Atl,
+    /// Date of the all-time low (`atl_date`)
+    AtlDate,
+    /// Time of CoinGecko's last update (`last_updated`)
+    LastUpdated,
+}
+
+/// Raw value of a field, before any display formatting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue<'a> {
+    /// Text such as a name or an RFC 3339 date
+    Text(&'a str),
+    /// Whole number, e.g. the rank
+    Integer(u64),
+    /// Price, amount or percentage
+    Number(f64),
+    /// CoinGecko didn't provide the value
+    Missing,
+}
+
+impl Field {
+    /// Every field, in the order used by `--fields all`
+    pub const ALL: [Field; 27] = [
+        Field::Rank,
+        Field::Id,
+        Field::Symbol,
+        Field::Name,
+        Field::Price,
+        Field::MarketCap,
+        Field::Fdv,
+        Field::Volume,
+        Field::High24h,
+        Field::Low24h,
+        Field::Change1h,
+        Field::Change24h,
+        Field::Change7d,
+        Field::Change14d,
+        Field::Change30d,
+        Field::Change60d,
+        Field::Change200d,
+        Field::Change1y,
+        Field::CirculatingSupply,
+        Field::TotalSupply,
+        Field::MaxSupply,
+        Field::Ath,
+        Field::AthChange,
+        Field::AthDate,
+        Field::Atl,
+        Field::AtlDate,
+        Field::LastUpdated,
+    ];
+
+    /// Stable machine-readable name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Rank => "rank",
+            Field::Id => "id",
+            Field::Symbol => "symbol",
+            Field::Name => "name",
+            Field::Price => "price",
+            Field::MarketCap => "market_cap",
+            Field::Fdv => "fdv",
+            Field::Volume => "volume",
+            Field::High24h => "high_24h",
+            Field::Low24h => "low_24h",
+            Field::Change1h => "change_1h",
+            Field::Change24h => "change_24h",
+            Field::Change7d => "change_7d",
+            Field::Change14d => "change_14d",
+            Field::Change30d => "change_30d",
+            Field::Change60d => "change_60d",
+            Field::Change200d => "change_200d",
+            Field::Change1y => "change_1y",
+            Field::CirculatingSupply => "circulating_supply",
+            Field::TotalSupply => "total_supply",
+            Field::MaxSupply => "max_supply",
+            Field::Ath => "ath",
+            Field::AthChange => "ath_change",
+            Field::AthDate => "ath_date",
+            Field::Atl => "atl",
+            Field::AtlDate => "atl_date",
+            Field::LastUpdated => "last_updated",
+        }
+    }
+
+    /// Human-readable column header
+    pub fn header(&self) -> &'static str {
+        match self {
+            Field::Rank => "#",
+            Field::Id => "ID",
+            Field::Symbol => "Coin",
+            Field::Name => "Name",
+            Field::Price => "Price",
+            Field::MarketCap => "Market Cap",
+            Field::Fdv => "FDV",
+            Field::Volume => "Volume",
+            Field::High24h => "High 24h",
+            Field::Low24h => "Low 24h",
+            Field::Change1h => "1h %",
+            Field::Change24h => "24h %",
+            Field::Change7d => "7d %",
+            Field::Change14d => "14d %",
+            Field::Change30d => "30d %",
+            Field::Change60d => "60d %",
+            Field::Change200d => "200d %",
+            Field::Change1y => "1y %",
+            Field::CirculatingSupply => "Circulating",
+            Field::TotalSupply => "Total Supply",
+            Field::MaxSupply => "Max Supply",
+            Field::Ath => "ATH",
+            Field::AthChange => "From ATH %",
+            Field::AthDate => "ATH Date",
+            Field::Atl => "ATL",
+            Field::AtlDate => "ATL Date",
+            Field::LastUpdated => "Last Updated",
+        }
+    }
+
+    /// Parse a field name, ignoring case, `_` and `-` (so `change24h` matches `change_24h`)
+    pub fn parse(s: &str) -> Option<Field> {
+        let wanted = normalize(s);
+        let alias = match wanted.as_str() {
+            "marketcaprank" => Some(Field::Rank),
+            "currentprice" => Some(Field::Price),
+            "totalvolume" => Some(Field::Volume),
+            "fullydilutedvaluation" => Some(Field::Fdv),
+            "athchangepercentage" => Some(Field::AthChange),
+            _ => None,
+        };
+        alias.or_else(|| Field::ALL.into_iter().find(|f| normalize(f.name()) == wanted))
+    }
+
+    /// Parse a comma-separated list of field names; `all` selects every field
+    pub fn parse_list(s: &str) -> Result<Vec<Field>, String> {
+        if s.trim().eq_ignore_ascii_case("all") {
+            return Ok(Field::ALL.to_vec());
+        }
+
+        let fields = s
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| Field::parse(name).ok_or_else(|| format!("Unknown field `{}`", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if fields.is_empty() {
+            return Err("No fields given".to_string());
+        }
+        Ok(fields)
+    }
+
+    /// Whether the field is an amount in the quote currency
+    pub fn is_monetary(&self) -> bool {
+        matches!(
+            self,
+            Field::Price
+                | Field::MarketCap
+                | Field::Fdv
+                | Field::Volume
+                | Field::High24h
+                | Field::Low24h
+                | Field::Ath
+                | Field::Atl
+        )
+    }
+
+    /// Whether values are numbers rather than text
+    pub fn is_numeric(&self) -> bool {
+        !matches!(
+            self,
+            Field::Id | Field::Symbol | Field::Name | Field::AthDate | Field::AtlDate | Field::LastUpdated
+        )
+    }
+
+    /// Natural sort direction: largest first for numbers, except rank
+    pub fn default_descending(&self) -> bool {
+        self.is_numeric() && *self != Field::Rank
+    }
+
+    /// Raw value of this field for `coin`
+    pub fn value<'a>(&self, coin: &'a CoinMarket) -> FieldValue<'a> {
+        let number = |v: Option<f64>| v.map_or(FieldValue::Missing, FieldValue::Number);
+        let text = |v: &'a Option<String>| v.as_deref().map_or(FieldValue::Missing, FieldValue::Text);
+
+        match self {
+            Field::Rank => coin
+                .market_cap_rank
+                .map_or(FieldValue::Missing, |r| FieldValue::Integer(r.into())),
+            Field::Id => FieldValue::Text(&coin.id),
+            Field::Symbol => FieldValue::Text(&coin.symbol),
+            Field::Name => FieldValue::Text(&coin.name),
+            Field::Price => number(coin.current_price),
+            Field::MarketCap => number(coin.market_cap),
+            Field::Fdv => number(coin.fully_diluted_valuation),
+            Field::Volume => number(coin.total_volume),
+            Field::High24h => number(coin.high_24h),
+            Field::Low24h => number(coin.low_24h),
+            Field::Change1h => number(coin.price_change_percentage_1h_in_currency),
+            Field::Change24h => number(coin.price_change_percentage_24h_in_currency),
+            Field::Change7d => number(coin.price_change_percentage_7d_in_currency),
+            Field::Change14d => number(coin.price_change_percentage_14d_in_currency),
+            Field::Change30d => number(coin.price_change_percentage_30d_in_currency),
+            Field::Change60d => number(coin.price_change_percentage_60d_in_currency),
+            Field::Change200d => number(coin.price_change_percentage_200d_in_currency),
+            Field::Change1y => number(coin.price_change_percentage_1y_in_currency),
+            Field::CirculatingSupply => number(coin.circulating_supply),
+            Field::TotalSupply => number(coin.total_supply),
+            Field::MaxSupply => number(coin.max_supply),
+            Field::Ath => number(coin.ath),
+            Field::AthChange => number(coin.ath_change_percentage),
+            Field::AthDate => text(&coin.ath_date),
+            Field::Atl => number(coin.atl),
+            Field::AtlDate => text(&coin.atl_date),
+            Field::LastUpdated => text(&coin.last_updated),
+        }
+    }
+
+    /// Value formatted for people, e.g. `1.23B` or `+4.56%`
+    pub fn display(&self, coin: &CoinMarket) -> String {
+        let value = self.value(coin);
+        match (self, value) {
+            (_, FieldValue::Missing) => "N/A".to_string(),
+            (Field::Symbol, FieldValue::Text(s)) => s.to_uppercase(),
+            (Field::AthDate | Field::AtlDate | Field::LastUpdated, FieldValue::Text(s)) => {
+                s.chars().take(16).collect::<String>().replace('T', " ")
+            }
+            (_, FieldValue::Text(s)) => s.to_string(),
+            (_, FieldValue::Integer(n)) => n.to_string(),
+            (Field::Price | Field::High24h | Field::Low24h | Field::Ath | Field::Atl, FieldValue::Number(n)) => {
+                format_price(n)
+            }
+            (
+                Field::Change1h
+                | Field::Change24h
+                | Field::Change7d
+                | Field::Change14d
+                | Field::Change30d
+                | Field::Change60d
+                | Field::Change200d
+                | Field::Change1y
+                | Field::AthChange,
+                FieldValue::Number(n),
+            ) => format_change(Some(n)),
+            (_, FieldValue::Number(n)) => format_large_number(n),
+        }
+    }
+}
+
+/// Stable sort by `field`; coins without a value always go last
+pub fn sort_coins(coins: &mut [CoinMarket], field: Field, descending: bool) {
+    coins.sort_by(|a, b| {
+        let (a, b) = (field.value(a), field.value(b));
+        let ordering = match (a, b) {
+            (FieldValue::Missing, FieldValue::Missing) => return Ordering::Equal,
+            (FieldValue::Missing, _) => return Ordering::Greater,
+            (_, FieldValue::Missing) => return Ordering::Less,
+            (FieldValue::Text(a), FieldValue::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            (FieldValue::Integer(a), FieldValue::Integer(b)) => a.cmp(&b),
+            (FieldValue::Number(a), FieldValue::Number(b)) => a.total_cmp(&b),
+            _ => Ordering::Equal,
+        };
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coins() -> Vec<CoinMarket> {
+        serde_json::from_str(
+            r#"[
+                {"id": "a", "symbol": "a", "name": "alpha", "current_price": 2.0},
+                {"id": "b", "symbol": "b", "name": "Beta"},
+                {"id": "c", "symbol": "c", "name": "gamma", "current_price": 10.0},
+                {"id": "d", "symbol": "d", "name": "Delta", "current_price": 1.0}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn ids(coins: &[CoinMarket]) -> Vec<&str> {
+        coins.iter().map(|c| c.id.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_names_and_aliases() {
+        assert_eq!(Field::parse("change_24h"), Some(Field::Change24h));
+        assert_eq!(Field::parse("Change-24H"), Some(Field::Change24h));
+        assert_eq!(Field::parse("change24h"), Some(Field::Change24h));
+        assert_eq!(Field::parse("market_cap_rank"), Some(Field::Rank));
+        assert_eq!(Field::parse("current_price"), Some(Field::Price));
+        assert_eq!(Field::parse("total_volume"), Some(Field::Volume));
+        assert_eq!(Field::parse("fully_diluted_valuation"), Some(Field::Fdv));
+        assert_eq!(Field::parse("ath_change_percentage"), Some(Field::AthChange));
+        for field in Field::ALL {
+            assert_eq!(Field::parse(field.name()), Some(field));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!(Field::parse("colour"), None);
+        assert_eq!(Field::parse(""), None);
+        assert_eq!(Field::parse_list("price, colour").unwrap_err(), "Unknown field `colour`");
+        assert_eq!(Field::parse_list(" , ").unwrap_err(), "No fields given");
+        assert_eq!(Field::parse_list("price,,rank").unwrap(), [Field::Price, Field::Rank]);
+        assert_eq!(Field::parse_list(" ALL ").unwrap(), Field::ALL);
+    }
+
+    #[test]
+    fn missing_values_sort_last_both_ways() {
+        let mut coins = coins();
+        sort_coins(&mut coins, Field::Price, false);
+        assert_eq!(ids(&coins), ["d", "a", "c", "b"]);
+        sort_coins(&mut coins, Field::Price, true);
+        assert_eq!(ids(&coins), ["c", "a", "d", "b"]);
+    }
+
+    #[test]
+    fn sorts_text_ignoring_case() {
+        let mut coins = coins();
+        sort_coins(&mut coins, Field::Name, false);
+        assert_eq!(ids(&coins), ["a", "b", "d", "c"]);
+    }
+}
@@ -0,0 +1,68 @@
+//! Human-friendly number formatting for prices, amounts and changes
+
+/// Format a price with more decimals for smaller values
+pub fn format_price(price: f64) -> String {
+    if price >= 1.0 {
+        format!("{:.2}", price)
+    } else if price >= 0.01 {
+        format!("{:.4}", price)
+    } else {
+        format!("{:.8}", price)
+    }
+}
+
+/// Format a large amount with a K/M/B/T suffix
+pub fn format_large_number(n: f64) -> String {
+    if n >= 1_000_000_000_000.0 {
+        format!("{:.2}T", n / 1_000_000_000_000.0)
+    } else if n >= 1_000_000_000.0 {
+        format!("{:.2}B", n / 1_000_000_000.0)
+    } else if n >= 1_000_000.0 {
+        format!("{:.2}M", n / 1_000_000.0)
+    } else if n >= 1_000.0 {
+        format!("{:.2}K", n / 1_000.0)
+    } else {
+        format!("{:.2}", n)
+    }
+}
+
+/// Format a percentage change with an arrow for its direction, e.g. `▲ 1.23%`
+pub fn format_percentage(value: Option<f64>) -> String {
+    match value {
+        Some(v) if v >= 0.0 => format!("▲ {:.2}%", v),
+        Some(v) => format!("▼ {:.2}%", v.abs()),
+        None => "N/A".to_string(),
+    }
+}
+
+/// Format a percentage change with both an arrow and a sign, e.g. `▲ +1.23%` or `▼ −1.23%`,
+/// and `● 0.00%` when it rounds to zero, so the direction doesn't depend on color
+pub fn format_percentage_signed(value: Option<f64>) -> String {
+    match value {
+        Some(v) if v.abs() < 0.005 => "● 0.00%".to_string(),
+        Some(v) if v > 0.0 => format!("▲ +{:.2}%", v),
+        Some(v) => format!("▼ −{:.2}%", v.abs()),
+        None => "N/A".to_string(),
+    }
+}
+
+/// Format a percentage change with an explicit sign, e.g. `+1.23%`
+pub fn format_change(value: Option<f64>) -> String {
+    match value {
+        Some(v) => format!("{:+.2}%", v),
+        None => "N/A".to_string(),
+    }
+}
+
+/// Format a converted amount with enough precision for small values, trimming trailing zeros
+pub fn format_amount(n: f64) -> String {
+    let decimals = if n.abs() >= 1000.0 {
+        2
+    } else if n.abs() >= 1.0 {
+        6
+    } else {
+        10
+    };
+    let s = format!("{:.*}", decimals, n);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
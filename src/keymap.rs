@@ -0,0 +1,521 @@
+//! Key bindings: what the keys do in the TUI, the default bindings and overrides from the config file
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Something a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Timeframe,
+    History,
+    Export,
+    Convert,
+    Heatmap,
+    Tab,
+    Theme,
+    Layout,
+    Refresh,
+    Help,
+    Palette,
+    Quit,
+}
+
+impl Action {
+    /// Every action with its name in the config file, in the order the help lists them
+    pub const ALL: [(&'static str, Action); 20] = [
+        ("up", Action::Up),
+        ("down", Action::Down),
+        ("left", Action::Left),
+        ("right", Action::Right),
+        ("page-up", Action::PageUp),
+        ("page-down", Action::PageDown),
+        ("top", Action::Top),
+        ("bottom", Action::Bottom),
+        ("timeframe", Action::Timeframe),
+        ("history", Action::History),
+        ("export", Action::Export),
+        ("convert", Action::Convert),
+        ("heatmap", Action::Heatmap),
+        ("tab", Action::Tab),
+        ("theme", Action::Theme),
+        ("layout", Action::Layout),
+        ("refresh", Action::Refresh),
+        ("help", Action::Help),
+        ("palette", Action::Palette),
+        ("quit", Action::Quit),
+    ];
+
+    /// Actions shown in the footer, most useful first since it's cut to the terminal width
+    pub const FOOTER: [Action; 14] = [
+        Action::Help,
+        Action::Palette,
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Timeframe,
+        Action::History,
+        Action::Export,
+        Action::Convert,
+        Action::Heatmap,
+        Action::Theme,
+        Action::Refresh,
+        Action::Quit,
+    ];
+
+    fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, action)| *action)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|(n, _)| *n).collect();
+                format!("Unknown action `{}`, expected one of {}", name, names.join(", "))
+            })
+    }
+
+    /// What the action does, for the help
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Up => "Move selection up",
+            Action::Down => "Move selection down",
+            Action::Left => "Move to the heatmap tile on the left",
+            Action::Right => "Move to the heatmap tile on the right",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::Top => "Go to top",
+            Action::Bottom => "Go to bottom",
+            Action::Timeframe => "Cycle chart timeframe",
+            Action::History => "Toggle chart between sparkline and recorded history",
+            Action::Export => "Export the table or chart to CSV, JSON or Markdown",
+            Action::Convert => "Convert between coins and currencies",
+            Action::Heatmap => "Toggle between the coin table and the market heatmap",
+            Action::Tab => "Switch between coins and details on small terminals",
+            Action::Theme => "Cycle color theme",
+            Action::Layout => "Cycle custom layouts from the config file",
+            Action::Refresh => "Refresh data",
+            Action::Help => "Show key bindings",
+            Action::Palette => "Open the command palette",
+            Action::Quit => "Quit",
+        }
+    }
+
+    /// Short name for the footer
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::Top => "Top",
+            Action::Bottom => "Bottom",
+            Action::Timeframe => "Timeframe",
+            Action::History => "History",
+            Action::Export => "Export",
+            Action::Convert => "Convert",
+            Action::Heatmap => "Heatmap",
+            Action::Tab => "Tab",
+            Action::Theme => "Theme",
+            Action::Layout => "Layout",
+            Action::Refresh => "Refresh",
+            Action::Help => "Help",
+            Action::Palette => "Commands",
+            Action::Quit => "Quit",
+        }
+    }
+
+    /// Keys bound to the action when the config file doesn't say otherwise
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Up => &["up", "k"],
+            Action::Down => &["down", "j"],
+            Action::Left => &["left", "h"],
+            Action::Right => &["right", "l"],
+            Action::PageUp => &["pgup"],
+            Action::PageDown => &["pgdn"],
+            Action::Top => &["g"],
+            Action::Bottom => &["G"],
+            Action::Timeframe => &["T", "t"],
+            Action::History => &["H"],
+            Action::Export => &["e"],
+            Action::Convert => &["c"],
+            Action::Heatmap => &["m", "M"],
+            Action::Tab => &["tab"],
+            Action::Theme => &["p"],
+            Action::Layout => &["L"],
+            Action::Refresh => &["r", "R"],
+            Action::Help => &["?"],
+            Action::Palette => &[":"],
+            Action::Quit => &["q", "esc"],
+        }
+    }
+}
+
+/// A key with the Ctrl and Alt modifiers held for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        // Shift is already part of the character, and of BackTab
+        Self {
+            code: key.code,
+            modifiers: key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT),
+        }
+    }
+}
+
+impl KeyChord {
+    /// Parse a key as written in the config file: a character, a name such as `up`, `pgdn`,
+    /// `tab`, `esc`, `space` or `f1`, optionally after `ctrl-` and/or `alt-`
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut rest = s;
+        let mut modifiers = KeyModifiers::NONE;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            if rest.len() > 5 && (lower.starts_with("ctrl-") || lower.starts_with("ctrl+")) {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = &rest[5..];
+            } else if rest.len() > 4 && (lower.starts_with("alt-") || lower.starts_with("alt+")) {
+                modifiers |= KeyModifiers::ALT;
+                rest = &rest[4..];
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pgup" | "pageup" => KeyCode::PageUp,
+                "pgdn" | "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("Unknown key `{}`", s)),
+                },
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        match self.code {
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+/// One or more keys pressed one after another, like `g g`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySequence(Vec<KeyChord>);
+
+impl KeySequence {
+    /// Parse keys separated by spaces
+    fn parse(s: &str) -> Result<Self, String> {
+        let keys = s.split_whitespace().map(KeyChord::parse).collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err("Empty key binding".to_string());
+        }
+        Ok(Self(keys))
+    }
+
+    /// Whether one of the sequences is the start of (or the same as) the other
+    fn overlaps(&self, other: &KeySequence) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a == b)
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+/// Keys for an action in the `[keys]` config section, either one or a list
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeysConfig {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// What the keys typed so far amount to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Action(Action),
+    /// The start of a longer sequence, wait for the next key
+    Pending,
+    None,
+}
+
+/// Key bindings in effect
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .flat_map(|(_, action)| {
+                action
+                    .default_keys()
+                    .iter()
+                    .map(|key| (KeySequence::parse(key).expect("valid default key"), *action))
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Default bindings with the actions in `overrides` bound to the given keys instead.
+    /// Default bindings that clash with the configured ones are dropped.
+    pub fn new(overrides: &BTreeMap<String, KeysConfig>) -> Result<Self> {
+        let mut configured: Vec<(KeySequence, Action)> = Vec::new();
+        for (name, keys) in overrides {
+            let action = Action::parse(name).map_err(anyhow::Error::msg)?;
+            let keys = match keys {
+                KeysConfig::One(key) => std::slice::from_ref(key),
+                KeysConfig::Many(keys) => keys.as_slice(),
+            };
+            for key in keys {
+                let sequence = KeySequence::parse(key)
+                    .map_err(|e| anyhow::anyhow!("{} in the keys for `{}`", e, name))?;
+                if let Some((other, other_action)) = configured.iter().find(|(s, _)| s.overlaps(&sequence)) {
+                    bail!(
+                        "`{}` for {} clashes with `{}` for {}",
+                        sequence,
+                        name,
+                        other,
+                        Action::ALL.iter().find(|(_, a)| a == other_action).map_or("", |(n, _)| n)
+                    );
+                }
+                configured.push((sequence, action));
+            }
+        }
+
+        let mut bindings: Vec<(KeySequence, Action)> = Self::default()
+            .bindings
+            .into_iter()
+            .filter(|(sequence, action)| {
+                !configured.iter().any(|(s, a)| a == action || s.overlaps(sequence))
+            })
+            .collect();
+        bindings.extend(configured);
+        Ok(Self { bindings })
+    }
+
+    /// Action for the keys typed so far
+    pub fn lookup(&self, keys: &[KeyChord]) -> Lookup {
+        let mut lookup = Lookup::None;
+        for (sequence, action) in &self.bindings {
+            if sequence.0 == keys {
+                return Lookup::Action(*action);
+            }
+            if sequence.0.starts_with(keys) {
+                lookup = Lookup::Pending;
+            }
+        }
+        lookup
+    }
+
+    /// Keys bound to `action`, in the order they were bound
+    pub fn keys(&self, action: Action) -> Vec<&KeySequence> {
+        self.bindings.iter().filter(|(_, a)| *a == action).map(|(s, _)| s).collect()
+    }
+
+    /// Each action with its keys joined for display, e.g. `↑/k`; unbound actions are left out
+    pub fn help(&self) -> Vec<(String, Action)> {
+        Action::ALL
+            .iter()
+            .filter_map(|(_, action)| {
+                let keys: Vec<String> = self.keys(*action).iter().map(|s| s.to_string()).collect();
+                (!keys.is_empty()).then(|| (keys.join("/"), *action))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        KeyChord { code, modifiers }
+    }
+
+    fn press(c: char) -> KeyChord {
+        chord(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn keymap(config: &str) -> Result<Keymap> {
+        let overrides: BTreeMap<String, KeysConfig> = toml::from_str(config)?;
+        Keymap::new(&overrides)
+    }
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        assert_eq!(KeyChord::parse("q"), Ok(press('q')));
+        assert_eq!(KeyChord::parse("G"), Ok(press('G')));
+        assert_eq!(KeyChord::parse("-"), Ok(press('-')));
+        assert_eq!(KeyChord::parse("PgDn"), Ok(chord(KeyCode::PageDown, KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("space"), Ok(press(' ')));
+        assert_eq!(KeyChord::parse("f12"), Ok(chord(KeyCode::F(12), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("ctrl-d"), Ok(chord(KeyCode::Char('d'), KeyModifiers::CONTROL)));
+        assert_eq!(
+            KeyChord::parse("Alt+Ctrl+up"),
+            Ok(chord(KeyCode::Up, KeyModifiers::CONTROL | KeyModifiers::ALT))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert_eq!(KeyChord::parse("shift-g"), Err("Unknown key `shift-g`".to_string()));
+        assert_eq!(KeyChord::parse("f13"), Err("Unknown key `f13`".to_string()));
+        assert_eq!(KeyChord::parse("ctrl-"), Err("Unknown key `ctrl-`".to_string()));
+        assert_eq!(KeyChord::parse(""), Err("Unknown key ``".to_string()));
+        assert_eq!(KeySequence::parse("  "), Err("Empty key binding".to_string()));
+    }
+
+    #[test]
+    fn key_events_match_parsed_keys() {
+        let event = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::from(event), press('G'));
+        let event = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::from(event), chord(KeyCode::Char('d'), KeyModifiers::CONTROL));
+    }
+
+    #[test]
+    fn sequences_overlap_when_one_starts_the_other() {
+        let seq = |s: &str| KeySequence::parse(s).unwrap();
+        assert!(seq("g").overlaps(&seq("g g")));
+        assert!(seq("g g").overlaps(&seq("g")));
+        assert!(seq("g t").overlaps(&seq("g t")));
+        assert!(!seq("g t").overlaps(&seq("g g")));
+        assert!(!seq("g").overlaps(&seq("G")));
+        assert_eq!(seq("ctrl-x  pgdn").to_string(), "Ctrl+x PgDn");
+    }
+
+    #[test]
+    fn default_keys_are_unambiguous() {
+        let keymap = Keymap::default();
+        for (i, (a, _)) in keymap.bindings.iter().enumerate() {
+            for (b, _) in &keymap.bindings[i + 1..] {
+                assert!(!a.overlaps(b), "`{}` and `{}` overlap", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn lookup_waits_for_sequences() {
+        let keymap = keymap(r#"top = "g g"
+bottom = "g e""#).unwrap();
+        assert_eq!(keymap.lookup(&[press('g')]), Lookup::Pending);
+        assert_eq!(keymap.lookup(&[press('g'), press('g')]), Lookup::Action(Action::Top));
+        assert_eq!(keymap.lookup(&[press('g'), press('e')]), Lookup::Action(Action::Bottom));
+        assert_eq!(keymap.lookup(&[press('g'), press('x')]), Lookup::None);
+        assert_eq!(keymap.lookup(&[press('j')]), Lookup::Action(Action::Down));
+        assert_eq!(keymap.lookup(&[press('z')]), Lookup::None);
+    }
+
+    #[test]
+    fn configured_keys_replace_defaults() {
+        let keymap = keymap(r#"quit = ["ctrl-q", "x"]
+help = "q""#).unwrap();
+        // `q` moved from quit to help, and `?` isn't bound any more
+        assert_eq!(keymap.lookup(&[press('q')]), Lookup::Action(Action::Help));
+        assert_eq!(keymap.lookup(&[press('?')]), Lookup::None);
+        assert_eq!(keymap.lookup(&[press('x')]), Lookup::Action(Action::Quit));
+        let quit: Vec<String> = keymap.keys(Action::Quit).iter().map(|s| s.to_string()).collect();
+        assert_eq!(quit, ["Ctrl+q", "x"]);
+    }
+
+    #[test]
+    fn configured_key_drops_a_default_it_clashes_with() {
+        // `g` on its own would swallow the `g g` sequence
+        let keymap = keymap(r#"export = "g g""#).unwrap();
+        assert!(keymap.keys(Action::Top).is_empty());
+        assert_eq!(keymap.lookup(&[press('g'), press('g')]), Lookup::Action(Action::Export));
+        assert_eq!(keymap.lookup(&[press('e')]), Lookup::None);
+    }
+
+    #[test]
+    fn rejects_bad_config() {
+        let error = |config: &str| keymap(config).unwrap_err().to_string();
+        assert_eq!(
+            error(r#"top = "g"
+bottom = "g g""#),
+            "`g` for top clashes with `g g` for bottom"
+        );
+        assert_eq!(error(r#"quit = ["q", "q"]"#), "`q` for quit clashes with `q` for quit");
+        assert_eq!(error(r#"top = "shift-g""#), "Unknown key `shift-g` in the keys for `top`");
+        assert_eq!(error(r#"top = """#), "Empty key binding in the keys for `top`");
+        assert!(error(r#"jump = "j""#).starts_with("Unknown action `jump`, expected one of up, down"));
+    }
+}
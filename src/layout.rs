@@ -0,0 +1,282 @@
+//! Custom screen layouts: trees of splits with named panels, loaded from the config file
+
+use anyhow::{bail, Context, Result};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use serde::Deserialize;
+
+/// Something that can be placed in a layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    /// Price chart of the selected coin
+    Chart,
+    /// Coin table, or the market heatmap after `m`
+    Table,
+    Price,
+    Changes,
+    Details,
+    Supply,
+}
+
+impl Panel {
+    const ALL: [(&'static str, Panel); 6] = [
+        ("chart", Panel::Chart),
+        ("table", Panel::Table),
+        ("price", Panel::Price),
+        ("changes", Panel::Changes),
+        ("details", Panel::Details),
+        ("supply", Panel::Supply),
+    ];
+
+    fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, panel)| *panel)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|(n, _)| *n).collect();
+                format!("Unknown panel `{}`, expected one of {}", name, names.join(", "))
+            })
+    }
+}
+
+/// Space a node takes along its parent's split
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Size {
+    /// An equal share of what the sized siblings leave
+    Fill,
+    /// Fixed number of rows or columns
+    Cells(u16),
+    Percent(u16),
+}
+
+impl Size {
+    fn constraint(self) -> Constraint {
+        match self {
+            Size::Fill => Constraint::Fill(1),
+            Size::Cells(n) => Constraint::Length(n),
+            Size::Percent(p) => Constraint::Percentage(p),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Panel(Panel),
+    Split(Direction, Vec<(Node, Size)>),
+}
+
+impl Node {
+    fn place(&self, area: Rect, out: &mut Vec<(Panel, Rect)>) {
+        match self {
+            Node::Panel(panel) => out.push((*panel, area)),
+            Node::Split(direction, children) => {
+                let areas = Layout::default()
+                    .direction(*direction)
+                    .constraints(children.iter().map(|(_, size)| size.constraint()))
+                    .split(area);
+                for ((child, _), area) in children.iter().zip(areas.iter()) {
+                    child.place(*area, out);
+                }
+            }
+        }
+    }
+}
+
+/// A `[[layouts]]` entry in the config file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub name: String,
+    /// e.g. `rows(chart 30%, cols(table, rows(price 5, changes)))`
+    pub layout: String,
+}
+
+/// A named screen layout
+#[derive(Debug, Clone)]
+pub struct ScreenLayout {
+    pub name: String,
+    root: Node,
+}
+
+impl ScreenLayout {
+    /// Parse a layout description: `rows(...)` stacks its children top to bottom and `cols(...)`
+    /// puts them side by side. Each child may be followed by a size, either a number of
+    /// rows/columns or a percentage; children without one share the rest equally.
+    pub fn parse(name: &str, layout: &str) -> Result<Self, String> {
+        let mut parser = Parser { input: layout, pos: 0 };
+        let root = parser.node()?;
+        parser.skip_space();
+        if parser.pos < layout.len() {
+            return Err(format!("Unexpected `{}` at position {}", &layout[parser.pos..], parser.pos + 1));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            root,
+        })
+    }
+
+    /// Where each panel goes in `area`
+    pub fn place(&self, area: Rect) -> Vec<(Panel, Rect)> {
+        let mut out = Vec::new();
+        self.root.place(area, &mut out);
+        out
+    }
+}
+
+/// Layouts from the config file, in the order they're listed
+pub fn load_layouts(configs: &[LayoutConfig]) -> Result<Vec<ScreenLayout>> {
+    let mut layouts: Vec<ScreenLayout> = Vec::new();
+    for config in configs {
+        if layouts.iter().any(|l| l.name.eq_ignore_ascii_case(&config.name)) {
+            bail!("Layout `{}` is defined twice", config.name);
+        }
+        let layout = ScreenLayout::parse(&config.name, &config.layout)
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("Invalid layout `{}`", config.name))?;
+        layouts.push(layout);
+    }
+    Ok(layouts)
+}
+
+/// Index of the layout called `name`, ignoring case
+pub fn find_layout(layouts: &[ScreenLayout], name: &str) -> Option<usize> {
+    layouts.iter().position(|l| l.name.eq_ignore_ascii_case(name))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn word(&mut self) -> &str {
+        self.skip_space();
+        let start = self.pos;
+        let rest = &self.input[start..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '%' || c == '-' || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &self.input[start..self.pos]
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        if self.input[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn node(&mut self) -> Result<Node, String> {
+        let word = self.word().to_string();
+        if word.is_empty() {
+            return Err(format!("Expected a panel, `rows(` or `cols(` at position {}", self.pos + 1));
+        }
+        let direction = match word.to_ascii_lowercase().as_str() {
+            "rows" => Direction::Vertical,
+            "cols" => Direction::Horizontal,
+            _ => return Panel::parse(&word).map(Node::Panel),
+        };
+        if !self.eat('(') {
+            return Err(format!("Expected `(` after `{}`", word));
+        }
+
+        let mut children = Vec::new();
+        loop {
+            let child = self.node()?;
+            let size = self.size()?;
+            children.push((child, size));
+            if self.eat(')') {
+                break;
+            }
+            if !self.eat(',') {
+                return Err(format!("Expected `,` or `)` in `{}(...)` at position {}", word, self.pos + 1));
+            }
+        }
+        Ok(Node::Split(direction, children))
+    }
+
+    fn size(&mut self) -> Result<Size, String> {
+        self.skip_space();
+        let start = self.pos;
+        let word = self.word();
+        if word.is_empty() {
+            return Ok(Size::Fill);
+        }
+        if !word.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!("Expected `,` or `)` before `{}` at position {}", word, start + 1));
+        }
+        let size = match word.strip_suffix('%') {
+            Some(p) => p.parse().ok().filter(|p| *p <= 100).map(Size::Percent),
+            None => word.parse().ok().map(Size::Cells),
+        };
+        size.ok_or_else(|| format!("Invalid size `{}` at position {}", word, start + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(layout: &str) -> String {
+        ScreenLayout::parse("test", layout).unwrap_err()
+    }
+
+    #[test]
+    fn parses_nested_splits_with_sizes() {
+        let layout = ScreenLayout::parse("trader", "rows(chart 40%, cols(table, rows(price 5, changes)))").unwrap();
+        let panels = layout.place(Rect::new(0, 0, 100, 50));
+        assert_eq!(
+            panels,
+            vec![
+                (Panel::Chart, Rect::new(0, 0, 100, 20)),
+                (Panel::Table, Rect::new(0, 20, 50, 30)),
+                (Panel::Price, Rect::new(50, 20, 50, 5)),
+                (Panel::Changes, Rect::new(50, 25, 50, 25)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_a_single_panel_ignoring_case_and_spaces() {
+        let layout = ScreenLayout::parse("big", "  Table ").unwrap();
+        assert_eq!(layout.place(Rect::new(0, 0, 80, 24)), vec![(Panel::Table, Rect::new(0, 0, 80, 24))]);
+        assert!(ScreenLayout::parse("x", "COLS( chart 30 ,details )").is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_layouts() {
+        assert!(error("").starts_with("Expected a panel"));
+        assert!(error("rows(chart, )").starts_with("Expected a panel"));
+        assert_eq!(error("rows chart"), "Expected `(` after `rows`");
+        assert_eq!(error("rows(chart"), "Expected `,` or `)` in `rows(...)` at position 11");
+        assert_eq!(error("rows(table foo)"), "Expected `,` or `)` before `foo` at position 12");
+        assert_eq!(error("table extra"), "Unexpected `extra` at position 7");
+        assert_eq!(error("rows(chart 40x)"), "Invalid size `40x` at position 12");
+        assert_eq!(error("rows(chart 120%)"), "Invalid size `120%` at position 12");
+        assert!(error("rows(chart, graph)").starts_with("Unknown panel `graph`, expected one of chart, table"));
+    }
+
+    #[test]
+    fn load_layouts_reports_the_bad_layout() {
+        let config = |name: &str, layout: &str| LayoutConfig {
+            name: name.to_string(),
+            layout: layout.to_string(),
+        };
+        let layouts = load_layouts(&[config("a", "table"), config("b", "rows(chart, table)")]).unwrap();
+        assert_eq!(find_layout(&layouts, "B"), Some(1));
+
+        let e = load_layouts(&[config("a", "rows(chart")]).unwrap_err();
+        assert_eq!(format!("{:#}", e), "Invalid layout `a`: Expected `,` or `)` in `rows(...)` at position 11");
+        let e = load_layouts(&[config("a", "table"), config("A", "chart")]).unwrap_err();
+        assert_eq!(e.to_string(), "Layout `A` is defined twice");
+    }
+}
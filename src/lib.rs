@@ -0,0 +1,39 @@
+//! CoinGecko client, market data model, formatting helpers and ratatui widgets behind
+//! the `coins` terminal price tracker.
+//!
+//! ```no_run
+//! use coins::api::CoinGeckoClient;
+//! use coins::format::{format_large_number, format_percentage, format_price};
+//!
+//! # async fn run() -> coins::api::Result<()> {
+//! let client = CoinGeckoClient::new();
+//! for coin in client.get_markets("usd").await?.iter().take(10) {
+//!     println!(
+//!         "{:<6} {:>12} {:>10} {}",
+//!         coin.symbol.to_uppercase(),
+//!         coin.current_price.map_or("N/A".to_string(), format_price),
+//!         coin.market_cap.map_or("N/A".to_string(), format_large_number),
+//!         format_percentage(coin.price_change_percentage_24h),
+//!     );
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The crate follows semantic versioning: items reachable from these modules are the
+//! public API, and breaking changes to them only happen in a new minor version while
+//! the major version is 0. The `cli` feature (on by default) only adds dependencies of
+//! the `coins` binary; library users can turn it off with `default-features = false`.
+
+#![warn(missing_docs)]
+
+pub mod api;
+pub mod cache;
+pub mod chart;
+pub mod convert;
+pub mod field;
+pub mod format;
+pub mod output;
+pub mod template;
+pub mod treemap;
+pub mod widgets;
@@ -1,7 +1,20 @@
-mod api;
 mod app;
+mod cli;
+mod config;
+mod converter;
+mod export;
+mod keymap;
+mod layout;
+mod metrics;
+mod palette;
+mod recorder;
+mod serve;
+mod theme;
 mod ui;
 
+// Library modules, imported at the crate root so `crate::api` etc. resolve in the modules above
+use coins::{api, cache, chart, convert, field, format, output, template, widgets};
+
 use std::io;
 use std::time::{Duration, Instant};
 
@@ -12,20 +25,66 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
+use tokio::{runtime::Runtime, task::JoinHandle};
 
-use api::CoinGeckoClient;
-use app::App;
+use api::{ApiError, CoinGeckoClient, CoinMarket};
+use app::{App, DialogAction, View};
+use config::Config;
+use convert::FiatRates;
+use export::ExportDialog;
+use keymap::{Action, Keymap};
+use layout::{find_layout, load_layouts};
+use palette::{Command, CommandPalette};
+use recorder::Recorder;
+use theme::{find_theme, load_themes, ChangeColors, ColorDepth};
+use widgets::Column;
 use ui::ui;
 
-const UPDATE_INTERVAL: Duration = Duration::from_secs(60); // 1 minute update interval
 const TICK_RATE: Duration = Duration::from_millis(200);
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+type FetchTask = JoinHandle<api::Result<Vec<CoinMarket>>>;
+type RatesTask = JoinHandle<api::Result<FiatRates>>;
+
+/// Fetch market data on the runtime without blocking the UI
+fn spawn_fetch(rt: &Runtime, client: &CoinGeckoClient, vs_currency: &str) -> FetchTask {
+    let client = client.clone();
+    let vs_currency = vs_currency.to_string();
+    rt.spawn(async move { client.get_markets(&vs_currency).await })
+}
 
-fn fetch_data_blocking(client: &CoinGeckoClient, vs_currency: &str) -> Result<Vec<api::CoinMarket>, String> {
-    // Create a new tokio runtime for this blocking call
-    let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
-    rt.block_on(async {
-        client.get_markets(vs_currency).await.map_err(|e| e.to_string())
-    })
+/// Fetch exchange rates against `base` for the converter and the currency command
+fn spawn_rates(rt: &Runtime, client: &CoinGeckoClient, base: &str) -> RatesTask {
+    let client = client.clone();
+    let base = base.to_string();
+    rt.spawn(async move { convert::fetch_fiat_rates(&client, &base).await })
+}
+
+/// Quote prices in `code` from now on, showing its cached markets until the next fetch
+fn switch_currency(app: &mut App, client: &CoinGeckoClient, code: String) -> Result<(), String> {
+    let cached = client.cached_markets(&code);
+    if cached.is_none() && app.offline {
+        return Err(format!("No cached prices in {}", code.to_uppercase()));
+    }
+    app.fiat_rates = FiatRates::base_only(&code);
+    app.vs_currency = code;
+    app.error = None;
+    match cached {
+        Some(cached) => {
+            app.set_coins(cached.data);
+            app.stale_since = Some(cached.fetched_at);
+        }
+        None => {
+            // Prices in the old currency would be mislabeled, so show nothing until the fetch
+            app.coins.clear();
+            app.selected_index = 0;
+            app.scroll_offset = 0;
+            app.stale_since = None;
+        }
+    }
+    app.update_chart_data();
+    app.show_toast(format!("Currency: {}", app.vs_currency.to_uppercase()), false);
+    Ok(())
 }
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -40,26 +99,95 @@ fn print_help() {
     println!();
     println!("USAGE:");
     println!("    coins [OPTIONS]");
+    println!("    coins <COMMAND> [ARGS]");
+    println!();
+    println!("COMMANDS:");
+    println!("    price <COIN>...  Print current prices, e.g. `coins price btc eth`");
+    println!("    list             Print the top 100 coins");
+    println!("    info <COIN>      Print details for one coin, e.g. `coins info solana`");
+    println!("    convert <AMOUNT> <FROM> [to] <TO>");
+    println!("                     Convert between coins and currencies, e.g. `coins convert 0.5 btc to eth`");
+    println!("    watch <COIN>...  Print a line per refresh until interrupted, e.g. `coins watch btc eth`");
+    println!("    serve            Serve Prometheus metrics and a local JSON API until interrupted");
+    println!("    bar <COIN>...    Print one status bar line, e.g. `coins bar btc --format \"{{symbol}} {{price:.0}}\"`");
+    println!();
+    println!("COMMAND OPTIONS:");
+    println!("    -c, --currency <CODE>  Quote currency (default: usd); serve accepts a list, e.g. usd,eur");
+    println!("    -n, --top <N>          list, serve: only the first N coins");
+    println!("    -s, --sort <FIELD>     list: sort by rank, price, market_cap, volume, change_1h,");
+    println!("                           change_24h, change_7d, change_30d, change_1y, name, ...");
+    println!("        --asc, --desc      list: sort direction");
+    println!("    -i, --interval <SECS>  watch, serve: seconds between refreshes (default: plan refresh interval)");
+    println!("        --cross <COIN=PRICE,...>");
+    println!("                           watch: print only when a price crosses one of the thresholds");
+    println!("        --format <TEMPLATE>");
+    println!("                           bar: line per coin, e.g. \"{{symbol}} {{price:.0}} {{change_24h:+.1}}%\"");
+    println!("        --separator <SEP>  bar: text between coins (default: two spaces)");
+    println!("        --max-age <SECS>   bar: use cached data up to this old (default: plan refresh interval)");
+    println!("        --addr <ADDR>      serve: address to listen on (default: 127.0.0.1:9100),");
+    println!("                           also accepted as --metrics-addr");
+    println!("        --waybar           bar: print waybar JSON with an up/down/mixed class");
+    println!("    -o, --output <FORMAT>  table (default), json, ndjson, csv, markdown or line (watch default)");
+    println!("    -f, --fields <LIST>    Comma-separated fields to print, or `all`");
+    println!("        --offline          Use cached data only");
+    println!();
+    println!("    Coins can be given by symbol, name or CoinGecko id.");
+    println!("    Commands exit with 0 on success, 1 if data couldn't be fetched or a coin");
+    println!("    wasn't found, and 2 for invalid arguments.");
     println!();
     println!("OPTIONS:");
     println!("    -h, --help       Print help information");
     println!("    -V, --version    Print version information");
+    println!("        --offline    Show cached data only, without network access");
+    println!("        --record     Record every refresh into the local history database");
+    println!("        --currency <CODE>");
+    println!("                     Quote currency (default: usd), e.g. eur, jpy or btc");
+    println!("        --theme <NAME>");
+    println!("                     dark (default), light, solarized, high-contrast, monochrome");
+    println!("                     or a custom theme from the config file");
+    println!("        --columns <LIST>");
+    println!("                     Comma-separated table columns: any field or `sparkline`");
+    println!();
+    println!("ENVIRONMENT:");
+    println!("    COINGECKO_PRO_API_KEY     CoinGecko Pro API key");
+    println!("    COINGECKO_DEMO_API_KEY    CoinGecko Demo API key");
+    println!("    COINGECKO_API_KEY         API key for the plan in COINGECKO_API_PLAN (demo or pro)");
+    println!("    NO_COLOR                  Draw without colors");
     println!();
     println!("CONTROLS:");
-    println!("    ↑/k              Move selection up");
-    println!("    ↓/j              Move selection down");
-    println!("    PgUp/PgDn        Page up/down");
-    println!("    g/G              Go to top/bottom");
-    println!("    T                Cycle chart timeframe");
-    println!("    r                Refresh data");
-    println!("    q/Esc            Quit");
+    // The keys from the config file, as the TUI would use them
+    let keymap = Config::load()
+        .and_then(|config| Keymap::new(&config.keys))
+        .unwrap_or_default();
+    for (keys, action) in keymap.help() {
+        println!("    {:<16} {}", keys, action.description());
+    }
+    println!("    Ctrl+C           Quit");
+    println!("    Click row/tile   Select coin");
+    println!("    Click header     Sort by column, again to reverse, a third time for rank order");
+    println!("    Click title      Cycle chart timeframe");
+    println!("    Scroll           Move selection");
+    println!("    Hover chart      Show price at that point");
+    println!();
+    println!("    Keys can be changed in the [keys] section of the config file. Every action can also");
+    println!("    be run from the `:` command palette, along with goto, currency and sort.");
 }
 
 fn main() -> Result<()> {
     // Handle command line arguments
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        match args[1].as_str() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first().filter(|arg| cli::COMMANDS.contains(&arg.as_str())) {
+        std::process::exit(cli::run(command, &args[1..]));
+    }
+
+    let mut offline = false;
+    let mut record = false;
+    let mut theme = None;
+    let mut currency = None;
+    let mut columns = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
             "-V" | "--version" => {
                 print_version();
                 return Ok(());
@@ -68,26 +196,102 @@ fn main() -> Result<()> {
                 print_help();
                 return Ok(());
             }
+            "--offline" => offline = true,
+            "--record" => record = true,
+            "--theme" => match args.next() {
+                Some(name) => theme = Some(name.clone()),
+                None => {
+                    eprintln!("Missing value for --theme");
+                    std::process::exit(1);
+                }
+            },
+            "--currency" => match args.next() {
+                Some(code) => currency = Some(code.to_lowercase()),
+                None => {
+                    eprintln!("Missing value for --currency");
+                    std::process::exit(1);
+                }
+            },
+            "--columns" => match args.next().map(|list| Column::parse_list(list)) {
+                Some(Ok(list)) => columns = Some(list),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("Missing value for --columns");
+                    std::process::exit(1);
+                }
+            },
             _ => {
-                eprintln!("Unknown option: {}", args[1]);
+                eprintln!("Unknown option: {}", arg);
                 eprintln!("Use --help for usage information");
                 std::process::exit(1);
             }
         }
     }
 
-    // First, test the API before setting up TUI
-    let client = CoinGeckoClient::new();
-        
-    let initial_coins = match fetch_data_blocking(&client, "usd") {
-        Ok(coins) => {
-            coins
-        }
-        Err(e) => {
-            eprintln!("Warning: Could not fetch initial data: {}", e);
-            Vec::new()
-        }
+    let mut config = Config::load()?;
+    config.recorder.enabled |= record;
+    let rt = Runtime::new()?;
+    let client = config.client(offline)?;
+
+    // Show the last cached data right away; fresh data is fetched in the background
+    let mut app = App::new();
+    app.api_plan = client.plan();
+    app.offline = offline;
+    if let Some(code) = currency.or(config.currency.as_ref().map(|c| c.to_lowercase())) {
+        app.fiat_rates = FiatRates::base_only(&code);
+        app.vs_currency = code;
+    }
+    if let Some(dir) = &config.export_dir {
+        app.export_dir = dir.clone();
+    }
+    app.columns = match columns {
+        Some(columns) => columns,
+        None => config.columns()?,
+    };
+    app.sparkline = config.sparkline;
+    app.themes = load_themes(&config.themes)?;
+    app.color_depth = config.color_depth.unwrap_or_else(ColorDepth::detect);
+    app.change_colors = config.change_colors;
+    app.change_signs = config.change_signs.unwrap_or(
+        config.change_colors == ChangeColors::Colorblind || app.color_depth == ColorDepth::None,
+    );
+    let theme = theme.or(config.theme.clone()).unwrap_or_else(|| "dark".to_string());
+    let Some(theme_index) = find_theme(&app.themes, &theme) else {
+        let names: Vec<&str> = app.themes.iter().map(|t| t.name.as_str()).collect();
+        eprintln!("Unknown theme `{}`, expected {}", theme, names.join(", "));
+        std::process::exit(1);
     };
+    app.set_theme(theme_index);
+    app.keymap = Keymap::new(&config.keys)?;
+    app.layouts = load_layouts(&config.layouts)?;
+    if let Some(layout) = &config.layout {
+        let Some(layout_index) = find_layout(&app.layouts, layout) else {
+            let names: Vec<&str> = app.layouts.iter().map(|l| l.name.as_str()).collect();
+            if names.is_empty() {
+                eprintln!("Unknown layout `{}`, no [[layouts]] are defined", layout);
+            } else {
+                eprintln!("Unknown layout `{}`, expected {}", layout, names.join(", "));
+            }
+            std::process::exit(1);
+        };
+        app.layout_index = Some(layout_index);
+    }
+    if config.recorder.enabled {
+        app.recorder = Some(Recorder::open(config.recorder.clone())?);
+    }
+    if let Some(cached) = client.cached_markets(&app.vs_currency) {
+        app.coins = cached.data;
+        app.stale_since = Some(cached.fetched_at);
+        app.loading = false;
+        app.update_chart_data();
+    } else if offline {
+        eprintln!("Error: {}", ApiError::NotCached);
+        eprintln!("{}", ApiError::NotCached.hint());
+        std::process::exit(1);
+    }
 
     // Setup terminal
     enable_raw_mode()?;
@@ -96,16 +300,7 @@ fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app with initial data
-    let mut app = App::new();
-    app.coins = initial_coins;
-    if !app.coins.is_empty() {
-        app.last_update = Some(Instant::now());
-        app.loading = false;
-        app.update_chart_data();
-    }
-    
-    let res = run_app(&mut terminal, app, client);
+    let res = run_app(&mut terminal, app, &rt, client);
 
     // Restore terminal
     disable_raw_mode()?;
@@ -126,100 +321,228 @@ fn main() -> Result<()> {
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     mut app: App,
+    rt: &Runtime,
     client: CoinGeckoClient,
 ) -> Result<()> {
     let mut last_tick = Instant::now();
-    let mut last_fetch = Instant::now();
+    let mut last_fetch: Option<Instant> = None;
+    let mut fetch: Option<FetchTask> = None;
+    let mut rates_fetch: Option<RatesTask> = None;
+    let update_interval = client.plan().refresh_interval();
 
     loop {
-        // Draw UI
-        terminal.draw(|f| ui(f, &app))?;
-
-        // Check if we need to fetch data (auto-refresh every minute)
-        if !app.loading && last_fetch.elapsed() >= UPDATE_INTERVAL {
-            app.loading = true;
-            terminal.draw(|f| ui(f, &app))?;
+        app.rate_limit = client.rate_limit_status();
+        if app.toast.as_ref().is_some_and(|t| t.shown_at.elapsed() >= TOAST_DURATION) {
+            app.toast = None;
+        }
 
-            match fetch_data_blocking(&client, &app.vs_currency) {
-                Ok(coins) => {
-                    app.coins = coins;
-                    app.last_update = Some(Instant::now());
-                    app.error_message = None;
-                    app.update_chart_data();
-                }
-                Err(e) => {
-                    app.error_message = Some(format!("Failed to fetch data: {}", e));
-                }
+        // Pick up the result of a finished background fetch
+        if let Some(task) = fetch.take_if(|task| task.is_finished()) {
+            let result = rt
+                .block_on(task)
+                .unwrap_or_else(|e| Err(ApiError::Network(e.to_string())));
+            apply_fetch(&mut app, result);
+        }
+        if let Some(task) = rates_fetch.take_if(|task| task.is_finished()) {
+            match rt.block_on(task).unwrap_or_else(|e| Err(ApiError::Network(e.to_string()))) {
+                Ok(rates) => app.fiat_rates = rates,
+                Err(e) => app.show_toast(format!("Exchange rates unavailable: {}", e), true),
             }
-            
-            app.loading = false;
-            last_fetch = Instant::now();
         }
 
+        // Fetch on startup, then on the plan's refresh interval (paused while rate limited)
+        let due = last_fetch.is_none_or(|last| last.elapsed() >= update_interval);
+        if !app.offline && fetch.is_none() && app.rate_limit.cooldown.is_none() && due {
+            fetch = Some(spawn_fetch(rt, &client, &app.vs_currency));
+            app.loading = true;
+            last_fetch = Some(Instant::now());
+        }
+
+        // Draw UI
+        terminal.draw(|f| ui(f, &mut app))?;
+
         // Handle input with timeout
         let timeout = TICK_RATE
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
 
         if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                // Calculate visible rows: total height - chart (20%) - footer (3) - table header (3) - borders
-                let total_height = terminal.size()?.height as usize;
-                let chart_height = total_height / 5; // 20%
-                let visible_rows = total_height.saturating_sub(chart_height + 10);
+            let event = event::read()?;
+            // Clicks and scrolling go to the screen only while no dialog is open
+            if let Event::Mouse(mouse) = event {
+                if app.export_dialog.is_none() && app.converter.is_none() && app.palette.is_none() {
+                    let prev_selected = app.selected_index;
+                    app.handle_mouse(mouse);
+                    if prev_selected != app.selected_index {
+                        app.update_scroll_offset(app.visible_rows);
+                        app.update_chart_data();
+                    }
+                }
+            }
+            if let Event::Key(key) = event {
+                // Ctrl+C quits from anywhere, dialogs included
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    app.should_quit = true;
+                    continue;
+                }
+                if let Some(dialog) = &mut app.export_dialog {
+                    match dialog.handle_key(key) {
+                        DialogAction::None => {}
+                        DialogAction::Cancel => app.export_dialog = None,
+                        DialogAction::Submit => {
+                            let dialog = app.export_dialog.take().expect("dialog is open");
+                            match export::export(&app, &dialog) {
+                                Ok(path) => {
+                                    app.export_dir = dialog.directory.into();
+                                    app.show_toast(format!("Exported to {}", path.display()), false);
+                                }
+                                Err(e) => app.show_toast(format!("Export failed: {:#}", e), true),
+                            }
+                        }
+                    }
+                    continue;
+                }
+                if let Some(converter) = &mut app.converter {
+                    if let DialogAction::Cancel = converter.handle_key(key) {
+                        app.converter = None;
+                    }
+                    continue;
+                }
+
+                // Any key closes the help overlay
+                if app.show_help {
+                    app.show_help = false;
+                    continue;
+                }
+                // Commands from the palette that match a key run like the key
+                let action = match app.palette.take() {
+                    Some(mut palette) => match palette.handle_key(key, &app) {
+                        DialogAction::None => {
+                            app.palette = Some(palette);
+                            None
+                        }
+                        DialogAction::Cancel => None,
+                        DialogAction::Submit => match palette.command(&app) {
+                            Ok(Command::Action(action)) => Some(action),
+                            Ok(Command::Currency(code)) if code == app.vs_currency => None,
+                            Ok(Command::Currency(code)) => {
+                                match switch_currency(&mut app, &client, code) {
+                                    Ok(()) => {
+                                        if let Some(task) = fetch.take() {
+                                            task.abort();
+                                        }
+                                        if let Some(task) = rates_fetch.take() {
+                                            task.abort();
+                                        }
+                                        last_fetch = None;
+                                    }
+                                    Err(e) => app.show_toast(e, true),
+                                }
+                                None
+                            }
+                            Ok(command) => {
+                                palette::run(&mut app, command);
+                                None
+                            }
+                            Err(e) => {
+                                app.show_toast(e, true);
+                                None
+                            }
+                        },
+                    },
+                    None => app.key_action(key),
+                };
+                let Some(action) = action else {
+                    continue;
+                };
+
+                // Rows the coin table showed in the last draw
+                let visible_rows = app.visible_rows;
                 let prev_selected = app.selected_index;
 
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
+                match action {
+                    Action::Quit => {
                         app.should_quit = true;
                     }
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        app.should_quit = true;
+                    Action::Help => {
+                        app.show_help = true;
                     }
-                    KeyCode::Up | KeyCode::Char('k') => {
+                    Action::Tab => {
+                        app.toggle_compact_tab();
+                    }
+                    Action::Heatmap => {
+                        app.toggle_view();
+                    }
+                    // Arrows move between neighbouring tiles in the heatmap
+                    Action::Left if app.view == View::Heatmap => {
+                        app.move_heatmap_selection(-1, 0);
+                    }
+                    Action::Right if app.view == View::Heatmap => {
+                        app.move_heatmap_selection(1, 0);
+                    }
+                    Action::Up if app.view == View::Heatmap => {
+                        app.move_heatmap_selection(0, -1);
+                    }
+                    Action::Down if app.view == View::Heatmap => {
+                        app.move_heatmap_selection(0, 1);
+                    }
+                    Action::Left | Action::Right => {}
+                    Action::Up => {
                         app.move_selection_up(visible_rows);
                     }
-                    KeyCode::Down | KeyCode::Char('j') => {
+                    Action::Down => {
                         app.move_selection_down(visible_rows);
                     }
-                    KeyCode::PageUp => {
+                    Action::PageUp => {
                         app.page_up(visible_rows);
                     }
-                    KeyCode::PageDown => {
+                    Action::PageDown => {
                         app.page_down(visible_rows);
                     }
-                    KeyCode::Char('g') => {
+                    Action::Top => {
                         app.go_to_top();
                     }
-                    KeyCode::Char('G') => {
+                    Action::Bottom => {
                         app.go_to_bottom();
                     }
-                    KeyCode::Char('t') | KeyCode::Char('T') => {
+                    Action::Timeframe => {
                         app.cycle_timeframe();
                         app.update_chart_data();
                     }
-                    KeyCode::Char('r') | KeyCode::Char('R') => {
-                        if !app.loading {
+                    Action::Theme => {
+                        app.cycle_theme();
+                    }
+                    Action::Layout => {
+                        app.cycle_layout();
+                    }
+                    Action::Export => {
+                        app.export_dialog = Some(ExportDialog::new(&app.export_dir));
+                    }
+                    Action::Convert => {
+                        app.open_converter();
+                        // Rates for currencies other than the quote currency are loaded on first use
+                        if !app.fiat_rates.is_loaded() && rates_fetch.is_none() {
+                            rates_fetch = Some(spawn_rates(rt, &client, &app.vs_currency));
+                        }
+                    }
+                    Action::Palette => {
+                        app.palette = Some(CommandPalette::new());
+                        // The currency command completes from the exchange rates
+                        if !app.offline && !app.fiat_rates.is_loaded() && rates_fetch.is_none() {
+                            rates_fetch = Some(spawn_rates(rt, &client, &app.vs_currency));
+                        }
+                    }
+                    Action::History => {
+                        app.toggle_chart_source();
+                        app.update_chart_data();
+                    }
+                    Action::Refresh => {
+                        if !app.offline && fetch.is_none() {
+                            fetch = Some(spawn_fetch(rt, &client, &app.vs_currency));
                             app.loading = true;
-                            terminal.draw(|f| ui(f, &app))?;
-                            
-                            match fetch_data_blocking(&client, &app.vs_currency) {
-                                Ok(coins) => {
-                                    app.coins = coins;
-                                    app.last_update = Some(Instant::now());
-                                    app.error_message = None;
-                                    app.update_chart_data();
-                                }
-                                Err(e) => {
-                                    app.error_message = Some(format!("Failed to fetch data: {}", e));
-                                }
-                            }
-                            
-                            app.loading = false;
-                            last_fetch = Instant::now();
+                            last_fetch = Some(Instant::now());
                         }
                     }
-                    _ => {}
                 }
 
                 // Update chart data if selection changed
@@ -239,3 +562,20 @@ fn run_app<B: ratatui::backend::Backend>(
         }
     }
 }
+
+fn apply_fetch(app: &mut App, result: api::Result<Vec<CoinMarket>>) {
+    match result {
+        Ok(coins) => {
+            app.set_coins(coins);
+            app.last_update = Some(Instant::now());
+            app.stale_since = None;
+            app.error = None;
+            app.record_snapshot();
+            app.update_chart_data();
+        }
+        Err(e) => {
+            app.error = Some(e);
+        }
+    }
+    app.loading = false;
+}
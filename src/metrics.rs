@@ -0,0 +1,202 @@
+//! Prometheus text exposition of the markets and API fetches behind `coins serve`
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::api::{FetchMetrics, LATENCY_BUCKETS};
+use crate::field::{Field, FieldValue};
+use crate::serve::Snapshot;
+
+/// Per-coin gauges: metric name, help text and the field it reports
+const COIN_GAUGES: [(&str, &str, Field); 3] = [
+    ("coins_price", "Current price in the quote currency", Field::Price),
+    ("coins_market_cap", "Market capitalisation in the quote currency", Field::MarketCap),
+    ("coins_volume_24h", "Trading volume over 24 hours in the quote currency", Field::Volume),
+];
+
+/// Price change windows reported by `coins_price_change_percent`
+const CHANGE_PERIODS: [(&str, Field); 3] = [
+    ("1h", Field::Change1h),
+    ("24h", Field::Change24h),
+    ("7d", Field::Change7d),
+];
+
+/// Render the latest markets and the client's fetch metrics in the Prometheus text format
+pub fn render(snapshot: &Snapshot, fetches: &BTreeMap<String, FetchMetrics>) -> String {
+    let mut out = String::new();
+    let coins = || {
+        snapshot
+            .markets
+            .iter()
+            .flat_map(|(currency, coins)| coins.iter().map(move |coin| (currency, coin)))
+    };
+    let coin_labels = |currency: &str, coin: &crate::api::CoinMarket| {
+        format!(
+            "id=\"{}\",symbol=\"{}\",currency=\"{}\"",
+            escape(&coin.id),
+            escape(&coin.symbol),
+            escape(currency)
+        )
+    };
+
+    for (name, help, field) in COIN_GAUGES {
+        header(&mut out, name, help, "gauge");
+        for (currency, coin) in coins() {
+            if let FieldValue::Number(value) = field.value(coin) {
+                let _ = writeln!(out, "{}{{{}}} {}", name, coin_labels(currency, coin), value);
+            }
+        }
+    }
+
+    header(&mut out, "coins_price_change_percent", "Price change in percent over the period", "gauge");
+    for (currency, coin) in coins() {
+        for (period, field) in CHANGE_PERIODS {
+            if let FieldValue::Number(value) = field.value(coin) {
+                let _ = writeln!(
+                    out,
+                    "coins_price_change_percent{{{},period=\"{}\"}} {}",
+                    coin_labels(currency, coin),
+                    period,
+                    value
+                );
+            }
+        }
+    }
+
+    header(
+        &mut out,
+        "coins_last_update_timestamp_seconds",
+        "Unix time of the last successful markets fetch",
+        "gauge",
+    );
+    for (currency, updated) in &snapshot.updated {
+        let _ = writeln!(
+            out,
+            "coins_last_update_timestamp_seconds{{currency=\"{}\"}} {}",
+            escape(currency),
+            updated.timestamp()
+        );
+    }
+
+    header(&mut out, "coins_api_fetches_total", "CoinGecko API fetches by endpoint and result", "counter");
+    for (endpoint, metrics) in fetches {
+        for (result, count) in [("success", metrics.successes), ("failure", metrics.failures)] {
+            let _ = writeln!(
+                out,
+                "coins_api_fetches_total{{endpoint=\"{}\",result=\"{}\"}} {}",
+                escape(endpoint),
+                result,
+                count
+            );
+        }
+    }
+
+    let name = "coins_api_fetch_duration_seconds";
+    header(&mut out, name, "Latency of CoinGecko API fetches, including retries", "histogram");
+    for (endpoint, metrics) in fetches {
+        let endpoint = escape(endpoint);
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(metrics.latency_buckets) {
+            let _ = writeln!(out, "{}_bucket{{endpoint=\"{}\",le=\"{}\"}} {}", name, endpoint, bound, count);
+        }
+        let total = metrics.successes + metrics.failures;
+        let _ = writeln!(out, "{}_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}", name, endpoint, total);
+        let _ = writeln!(
+            out,
+            "{}_sum{{endpoint=\"{}\"}} {}",
+            name,
+            endpoint,
+            metrics.latency_sum.as_secs_f64()
+        );
+        let _ = writeln!(out, "{}_count{{endpoint=\"{}\"}} {}", name, endpoint, total);
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value: backslash, double quote and newline
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    fn snapshot() -> Snapshot {
+        let coins = serde_json::from_str(
+            r#"[{"id": "we\"ird\\coin", "symbol": "w\nc", "name": "Weird", "current_price": 1.5,
+                 "price_change_percentage_24h_in_currency": -2.0}]"#,
+        )
+        .unwrap();
+        let mut snapshot = Snapshot::default();
+        snapshot.markets.insert("usd".to_string(), coins);
+        snapshot.updated.insert("usd".to_string(), Utc.timestamp_opt(1_700_000_000, 0).unwrap());
+        snapshot
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape(r#"a\b"c"#), r#"a\\b\"c"#);
+        assert_eq!(escape("a\nb"), "a\\nb");
+        let out = render(&snapshot(), &BTreeMap::new());
+        assert!(out.contains(r#"coins_price{id="we\"ird\\coin",symbol="w\nc",currency="usd"} 1.5"#));
+    }
+
+    #[test]
+    fn writes_help_and_type_for_every_metric() {
+        let out = render(&Snapshot::default(), &BTreeMap::new());
+        for (name, kind) in [
+            ("coins_price", "gauge"),
+            ("coins_market_cap", "gauge"),
+            ("coins_volume_24h", "gauge"),
+            ("coins_price_change_percent", "gauge"),
+            ("coins_last_update_timestamp_seconds", "gauge"),
+            ("coins_api_fetches_total", "counter"),
+            ("coins_api_fetch_duration_seconds", "histogram"),
+        ] {
+            assert!(out.contains(&format!("# HELP {} ", name)), "{}", name);
+            assert!(out.contains(&format!("# TYPE {} {}\n", name, kind)), "{}", name);
+        }
+        assert!(out.lines().all(|line| line.starts_with('#')));
+    }
+
+    #[test]
+    fn skips_missing_values() {
+        let out = render(&snapshot(), &BTreeMap::new());
+        assert!(!out.contains("coins_market_cap{"));
+        assert!(!out.contains("coins_volume_24h{"));
+        let changes: Vec<&str> = out.lines().filter(|l| l.starts_with("coins_price_change_percent{")).collect();
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].ends_with(",period=\"24h\"} -2"));
+        assert!(out.contains("coins_last_update_timestamp_seconds{currency=\"usd\"} 1700000000\n"));
+    }
+
+    #[test]
+    fn renders_fetch_histogram() {
+        let mut metrics = FetchMetrics { successes: 2, failures: 1, ..Default::default() };
+        metrics.latency_buckets[0] = 1;
+        metrics.latency_buckets[LATENCY_BUCKETS.len() - 1] = 3;
+        metrics.latency_sum = Duration::from_millis(1500);
+        let fetches = BTreeMap::from([("/coins/markets".to_string(), metrics)]);
+        let out = render(&Snapshot::default(), &fetches);
+        assert!(out.contains("coins_api_fetches_total{endpoint=\"/coins/markets\",result=\"success\"} 2\n"));
+        assert!(out.contains("coins_api_fetches_total{endpoint=\"/coins/markets\",result=\"failure\"} 1\n"));
+        assert!(out.contains(&format!(
+            "coins_api_fetch_duration_seconds_bucket{{endpoint=\"/coins/markets\",le=\"{}\"}} 1\n",
+            LATENCY_BUCKETS[0]
+        )));
+        assert!(out.contains("coins_api_fetch_duration_seconds_bucket{endpoint=\"/coins/markets\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("coins_api_fetch_duration_seconds_sum{endpoint=\"/coins/markets\"} 1.5\n"));
+        assert!(out.contains("coins_api_fetch_duration_seconds_count{endpoint=\"/coins/markets\"} 3\n"));
+    }
+}
@@ -0,0 +1,417 @@
+//! Writers for coins and price series in table, JSON, CSV, Markdown and line formats
+
+use std::io::{self, Write};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::value::RawValue;
+
+use crate::api::CoinMarket;
+use crate::field::{Field, FieldValue};
+
+/// How non-interactive commands print coins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned plain-text columns
+    Table,
+    /// A JSON array of objects
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+    /// A GitHub-flavored Markdown table
+    Markdown,
+    /// All coins on one compact line, e.g. `BTC 67000.12 +2.50%  ETH 3200.50 -1.20%`
+    Line,
+}
+
+impl OutputFormat {
+    /// File extension used when exporting
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Table | OutputFormat::Line => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Markdown => "md",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "line" => Ok(OutputFormat::Line),
+            _ => Err(format!(
+                "Unknown output format `{}`, expected table, json, ndjson, csv, markdown or line",
+                s
+            )),
+        }
+    }
+}
+
+/// Write a list of coins, one row or record per coin
+pub fn write_coins<W: Write>(
+    out: &mut W,
+    coins: &[CoinMarket],
+    fields: &[Field],
+    format: OutputFormat,
+    vs_currency: &str,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => write_table(out, coins, fields, vs_currency),
+        OutputFormat::Markdown => {
+            let headers: Vec<String> = fields.iter().map(|f| header(f, vs_currency)).collect();
+            let rows: Vec<Vec<String>> = coins
+                .iter()
+                .map(|coin| fields.iter().map(|field| field.display(coin)).collect())
+                .collect();
+            write_markdown(out, &headers, fields.iter().map(Field::is_numeric), &rows)
+        }
+        OutputFormat::Json => {
+            write!(out, "[")?;
+            for (i, coin) in coins.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+                write!(out, "\n  {}", json_object(coin, fields))?;
+            }
+            writeln!(out, "{}]", if coins.is_empty() { "" } else { "\n" })
+        }
+        OutputFormat::Ndjson => {
+            for coin in coins {
+                writeln!(out, "{}", json_object(coin, fields))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Csv => {
+            let header: Vec<&str> = fields.iter().map(Field::name).collect();
+            writeln!(out, "{}", header.join(","))?;
+            for coin in coins {
+                let row: Vec<String> = fields.iter().map(|f| csv_value(f.value(coin))).collect();
+                writeln!(out, "{}", row.join(","))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Line => {
+            let coins: Vec<String> = coins
+                .iter()
+                .map(|coin| {
+                    let values: Vec<String> = fields.iter().map(|f| f.display(coin)).collect();
+                    values.join(" ")
+                })
+                .collect();
+            writeln!(out, "{}", coins.join("  "))
+        }
+    }
+}
+
+/// Write a single coin: a JSON object rather than an array, or a vertical list for tables
+pub fn write_coin<W: Write>(
+    out: &mut W,
+    coin: &CoinMarket,
+    fields: &[Field],
+    format: OutputFormat,
+    vs_currency: &str,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            let currency = vs_currency.to_uppercase();
+            writeln!(out, "{} ({})", coin.name, coin.symbol.to_uppercase())?;
+            for field in fields {
+                let label = if *field == Field::Rank { "Rank" } else { field.header() };
+                let value = field.display(coin);
+                if field.is_monetary() && value != "N/A" {
+                    writeln!(out, "{:<14} {} {}", label, value, currency)?;
+                } else {
+                    writeln!(out, "{:<14} {}", label, value)?;
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Json | OutputFormat::Ndjson => writeln!(out, "{}", json_object(coin, fields)),
+        OutputFormat::Csv | OutputFormat::Markdown | OutputFormat::Line => {
+            write_coins(out, std::slice::from_ref(coin), fields, format, vs_currency)
+        }
+    }
+}
+
+/// Write a price series as `time,price` records
+pub fn write_series<W: Write>(
+    out: &mut W,
+    points: &[(DateTime<Utc>, f64)],
+    format: OutputFormat,
+    vs_currency: &str,
+) -> io::Result<()> {
+    let time = |t: &DateTime<Utc>| t.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let price = |p: f64| format_number(FieldValue::Number(p)).unwrap_or_default();
+    let object = |(t, p): &(DateTime<Utc>, f64)| {
+        let point = [("time", Json(FieldValue::Text(&time(t)))), ("price", Json(FieldValue::Number(*p)))];
+        to_json(&JsonMap(&point))
+    };
+
+    match format {
+        OutputFormat::Json => {
+            let records: Vec<String> = points.iter().map(|p| format!("\n  {}", object(p))).collect();
+            writeln!(
+                out,
+                "[{}{}]",
+                records.join(","),
+                if points.is_empty() { "" } else { "\n" }
+            )
+        }
+        OutputFormat::Ndjson => {
+            for point in points {
+                writeln!(out, "{}", object(point))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "time,price")?;
+            for (t, p) in points {
+                writeln!(out, "{},{}", time(t), price(*p))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Line => {
+            for (t, p) in points {
+                writeln!(out, "{} {}", time(t), price(*p))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Table | OutputFormat::Markdown => {
+            let headers = ["Time".to_string(), format!("Price ({})", vs_currency.to_uppercase())];
+            let rows: Vec<Vec<String>> = points
+                .iter()
+                .map(|(t, p)| vec![t.format("%Y-%m-%d %H:%M").to_string(), price(*p)])
+                .collect();
+            write_markdown(out, &headers, [false, true], &rows)
+        }
+    }
+}
+
+fn header(field: &Field, vs_currency: &str) -> String {
+    if field.is_monetary() {
+        format!("{} ({})", field.header(), vs_currency.to_uppercase())
+    } else {
+        field.header().to_string()
+    }
+}
+
+/// GitHub-flavoured Markdown table, right-aligning numeric columns
+fn write_markdown<W: Write>(
+    out: &mut W,
+    headers: &[String],
+    numeric: impl IntoIterator<Item = bool>,
+    rows: &[Vec<String>],
+) -> io::Result<()> {
+    let escape = |cell: &String| cell.replace('|', "\\|");
+    let line = |cells: &[String]| format!("| {} |", cells.iter().map(escape).collect::<Vec<_>>().join(" | "));
+    let align: Vec<&str> = numeric
+        .into_iter()
+        .map(|numeric| if numeric { "--:" } else { ":--" })
+        .collect();
+
+    writeln!(out, "{}", line(headers))?;
+    writeln!(out, "| {} |", align.join(" | "))?;
+    for row in rows {
+        writeln!(out, "{}", line(row))?;
+    }
+    Ok(())
+}
+
+/// Plain-text table with numbers right-aligned
+fn write_table<W: Write>(
+    out: &mut W,
+    coins: &[CoinMarket],
+    fields: &[Field],
+    vs_currency: &str,
+) -> io::Result<()> {
+    let headers: Vec<String> = fields.iter().map(|f| header(f, vs_currency)).collect();
+    let rows: Vec<Vec<String>> = coins
+        .iter()
+        .map(|coin| fields.iter().map(|field| field.display(coin)).collect())
+        .collect();
+
+    let widths: Vec<usize> = (0..fields.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(headers[i].chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |cells: &[String]| {
+        let line = cells
+            .iter()
+            .zip(fields)
+            .zip(&widths)
+            .map(|((cell, field), width)| {
+                if field.is_numeric() {
+                    format!("{:>width$}", cell, width = width)
+                } else {
+                    format!("{:<width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        line.trim_end().to_string()
+    };
+
+    writeln!(out, "{}", format_row(&headers))?;
+    for row in &rows {
+        writeln!(out, "{}", format_row(row))?;
+    }
+    Ok(())
+}
+
+/// JSON object with keys in `fields` order
+fn json_object(coin: &CoinMarket, fields: &[Field]) -> String {
+    let members: Vec<(&str, Json)> = fields.iter().map(|field| (field.name(), Json(field.value(coin)))).collect();
+    to_json(&JsonMap(&members))
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("field values always serialize")
+}
+
+/// Object members serialized in the given order
+struct JsonMap<'a, 'b>(&'a [(&'a str, Json<'b>)]);
+
+impl Serialize for JsonMap<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// A field value as JSON, with numbers written by `format_number` and non-finite ones as `null`
+struct Json<'a>(FieldValue<'a>);
+
+impl Serialize for Json<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            FieldValue::Text(s) => serializer.serialize_str(s),
+            value => match format_number(value) {
+                Some(number) => RawValue::from_string(number)
+                    .map_err(serde::ser::Error::custom)?
+                    .serialize(serializer),
+                None => serializer.serialize_none(),
+            },
+        }
+    }
+}
+
+/// Quote and escape `s` as a JSON string
+pub fn json_string(s: &str) -> String {
+    serde_json::to_string(s).expect("strings always serialize")
+}
+
+fn csv_value(value: FieldValue) -> String {
+    match value {
+        FieldValue::Text(s) if s.contains([',', '"', '\n', '\r']) => {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        }
+        FieldValue::Text(s) => s.to_string(),
+        number => format_number(number).unwrap_or_default(),
+    }
+}
+
+/// Numbers are written the same way in every format: plain decimal notation,
+/// shortest round-trip digits, never exponents. Percentages are in percent, e.g. `2.5`.
+fn format_number(value: FieldValue) -> Option<String> {
+    match value {
+        FieldValue::Integer(n) => Some(n.to_string()),
+        FieldValue::Number(n) if n.is_finite() => Some(format!("{}", n)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn coins() -> Vec<CoinMarket> {
+        serde_json::from_str(
+            r#"[
+                {"id": "bitcoin", "symbol": "btc", "name": "Bit, \"coin\"", "current_price": 0.0000001,
+                 "market_cap_rank": 1, "price_change_percentage_24h_in_currency": 2.5},
+                {"id": "pipe", "symbol": "p|p", "name": "Line\nbreak", "current_price": 1e21}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn write(coins: &[CoinMarket], fields: &[Field], format: OutputFormat) -> String {
+        let mut out = Vec::new();
+        write_coins(&mut out, coins, fields, format, "usd").unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_quotes_and_escapes_text() {
+        let out = write(&coins(), &[Field::Name, Field::Price, Field::Change24h], OutputFormat::Csv);
+        assert_eq!(
+            out,
+            "name,price,change_24h\n\"Bit, \"\"coin\"\"\",0.0000001,2.5\n\"Line\nbreak\",1000000000000000000000,\n"
+        );
+    }
+
+    #[test]
+    fn json_keeps_field_order_and_escapes() {
+        let fields = [Field::Rank, Field::Name, Field::Price, Field::Change24h];
+        let out = write(&coins(), &fields, OutputFormat::Json);
+        assert_eq!(
+            out,
+            "[\n  {\"rank\":1,\"name\":\"Bit, \\\"coin\\\"\",\"price\":0.0000001,\"change_24h\":2.5},\n  \
+             {\"rank\":null,\"name\":\"Line\\nbreak\",\"price\":1000000000000000000000,\"change_24h\":null}\n]\n"
+        );
+        let parsed: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed[1]["name"], "Line\nbreak");
+        assert_eq!(write(&[], &fields, OutputFormat::Json), "[]\n");
+
+        let ndjson = write(&coins(), &[Field::Id], OutputFormat::Ndjson);
+        assert_eq!(ndjson, "{\"id\":\"bitcoin\"}\n{\"id\":\"pipe\"}\n");
+    }
+
+    #[test]
+    fn json_writes_non_finite_numbers_as_null() {
+        let mut coins = coins();
+        coins[0].current_price = Some(f64::NAN);
+        coins[1].current_price = Some(f64::INFINITY);
+        let out = write(&coins, &[Field::Price], OutputFormat::Ndjson);
+        assert_eq!(out, "{\"price\":null}\n{\"price\":null}\n");
+
+        let t = Utc.timestamp_opt(0, 0).unwrap();
+        let mut out = Vec::new();
+        write_series(&mut out, &[(t, 1.5), (t, f64::NAN)], OutputFormat::Json, "usd").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n  {\"time\":\"1970-01-01T00:00:00Z\",\"price\":1.5},\n  \
+             {\"time\":\"1970-01-01T00:00:00Z\",\"price\":null}\n]\n"
+        );
+    }
+
+    #[test]
+    fn markdown_aligns_numbers_and_escapes_pipes() {
+        let out = write(&coins()[1..], &[Field::Symbol, Field::Price], OutputFormat::Markdown);
+        assert_eq!(
+            out,
+            "| Coin | Price (USD) |\n| :-- | --: |\n| P\\|P | 1000000000000000000000.00 |\n"
+        );
+    }
+}
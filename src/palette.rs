@@ -0,0 +1,471 @@
+//! `:` command palette: every key binding action by name, plus commands that take an argument,
+//! with fuzzy matching and completion
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::api::find_coin;
+use crate::app::{App, DialogAction};
+use crate::chart::ChartTimeframe;
+use crate::export::{format_label, ExportDialog, ExportTarget, FORMATS};
+use crate::field::Field;
+use crate::keymap::Action;
+use crate::layout::find_layout;
+use crate::output::OutputFormat;
+use crate::theme::find_theme;
+
+/// Currencies offered before the full list has been loaded from CoinGecko
+const COMMON_CURRENCIES: [&str; 12] = [
+    "usd", "eur", "gbp", "jpy", "cny", "krw", "inr", "aud", "cad", "chf", "btc", "eth",
+];
+
+/// Commands that aren't key binding actions
+const EXTRA_COMMANDS: [(&str, &str, &str); 3] = [
+    ("currency", "<code>", "Quote prices in another currency"),
+    ("goto", "<coin>", "Select a coin by symbol, name or id"),
+    ("sort", "<field> [asc|desc]", "Sort the coin table, `sort rank` for the usual order"),
+];
+
+/// What the palette was asked to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Same as pressing the action's key
+    Action(Action),
+    Currency(String),
+    Timeframe(ChartTimeframe),
+    Theme(usize),
+    /// `None` for the built-in layout
+    Layout(Option<usize>),
+    Export(ExportTarget, OutputFormat),
+    Goto(usize),
+    Sort(Field, bool),
+}
+
+/// A completion for the word being typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub text: String,
+    pub detail: String,
+}
+
+impl Suggestion {
+    fn new(text: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// State of the command palette popup
+#[derive(Debug, Clone, Default)]
+pub struct CommandPalette {
+    pub input: String,
+    /// Highlighted suggestion
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, app: &App) -> DialogAction {
+        match key.code {
+            KeyCode::Esc => return DialogAction::Cancel,
+            KeyCode::Enter => {
+                // A missing argument is reported rather than filled in with the first suggestion
+                if !self.split().1.is_empty() {
+                    self.complete(app);
+                }
+                return DialogAction::Submit;
+            }
+            KeyCode::Tab if self.complete(app) => self.input.push(' '),
+            KeyCode::Up | KeyCode::BackTab => {
+                let count = self.suggestions(app).len().max(1);
+                self.selected = (self.selected + count - 1) % count;
+            }
+            KeyCode::Down => {
+                let count = self.suggestions(app).len().max(1);
+                self.selected = (self.selected + 1) % count;
+            }
+            // Backspace on an empty line closes the palette, like in vi
+            KeyCode::Backspace if self.input.is_empty() => return DialogAction::Cancel,
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        DialogAction::None
+    }
+
+    /// Replace the word being typed with the highlighted suggestion, unless it's already complete.
+    /// Returns whether the word is now a known one.
+    fn complete(&mut self, app: &App) -> bool {
+        let suggestions = self.suggestions(app);
+        let (_, word) = self.split();
+        if suggestions.iter().any(|s| s.text.eq_ignore_ascii_case(word)) {
+            return true;
+        }
+        let Some(suggestion) = suggestions.get(self.selected).or(suggestions.first()) else {
+            return false;
+        };
+        let keep = self.input.len() - word.len();
+        self.input.truncate(keep);
+        self.input.push_str(&suggestion.text);
+        self.selected = 0;
+        true
+    }
+
+    /// Words before the one being typed, and that word (empty right after a space)
+    fn split(&self) -> (Vec<&str>, &str) {
+        let (done, word) = match self.input.rfind(char::is_whitespace) {
+            Some(i) => (&self.input[..i], &self.input[i + 1..]),
+            None => ("", self.input.as_str()),
+        };
+        (done.split_whitespace().collect(), word)
+    }
+
+    /// Completions for the word being typed, best match first
+    pub fn suggestions(&self, app: &App) -> Vec<Suggestion> {
+        let (done, word) = self.split();
+        let candidates = match done.split_first() {
+            None => commands(),
+            Some((command, args)) => arguments(&command.to_lowercase(), args.len(), app),
+        };
+
+        let mut scored: Vec<(i32, Suggestion)> = candidates
+            .into_iter()
+            .filter_map(|s| {
+                // Coins also match by name
+                let score = fuzzy_score(word, &s.text).or_else(|| fuzzy_score(word, &s.detail).map(|n| n - 1000))?;
+                Some((score, s))
+            })
+            .collect();
+        // Nothing typed yet: keep the commands in the order the help lists them
+        if !word.is_empty() {
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        }
+        scored.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// Parse the typed line
+    pub fn command(&self, app: &App) -> Result<Command, String> {
+        let words: Vec<&str> = self.input.split_whitespace().collect();
+        let Some((name, args)) = words.split_first() else {
+            return Err("No command given".to_string());
+        };
+        let name = name.to_lowercase();
+        let arg = |i: usize| args.get(i).copied();
+
+        let command = match (name.as_str(), arg(0)) {
+            ("currency", Some(code)) => {
+                let code = code.to_lowercase();
+                if app.fiat_rates.is_loaded() && !app.fiat_rates.currencies().any(|c| c == code) {
+                    return Err(format!("CoinGecko doesn't quote prices in `{}`", code));
+                }
+                Command::Currency(code)
+            }
+            ("currency", None) => return Err("Usage: currency <code>".to_string()),
+            ("goto", Some(_)) => {
+                let query = args.join(" ");
+                let coin = find_coin(&app.coins, &query).ok_or_else(|| format!("Coin `{}` not found", query))?;
+                Command::Goto(app.coins.iter().position(|c| c.id == coin.id).unwrap_or(0))
+            }
+            ("goto", None) => return Err("Usage: goto <coin>".to_string()),
+            ("sort", Some(field)) => {
+                let field = Field::parse(field).ok_or_else(|| format!("Unknown field `{}`", field))?;
+                let descending = match arg(1).map(|d| d.to_lowercase()) {
+                    None => field.default_descending(),
+                    Some(d) if d == "asc" => false,
+                    Some(d) if d == "desc" => true,
+                    Some(d) => return Err(format!("Expected asc or desc, not `{}`", d)),
+                };
+                Command::Sort(field, descending)
+            }
+            ("sort", None) => return Err("Usage: sort <field> [asc|desc]".to_string()),
+            ("timeframe", Some(tf)) => {
+                Command::Timeframe(ChartTimeframe::parse(tf).ok_or_else(|| format!("Unknown timeframe `{}`", tf))?)
+            }
+            ("theme", Some(theme)) => {
+                Command::Theme(find_theme(&app.themes, theme).ok_or_else(|| format!("Unknown theme `{}`", theme))?)
+            }
+            ("layout", Some(layout)) if layout.eq_ignore_ascii_case("default") => Command::Layout(None),
+            ("layout", Some(layout)) => Command::Layout(Some(
+                find_layout(&app.layouts, layout).ok_or_else(|| format!("Unknown layout `{}`", layout))?,
+            )),
+            ("export", Some(target)) => {
+                let target = match target.to_lowercase().as_str() {
+                    "table" => ExportTarget::Table,
+                    "chart" => ExportTarget::Chart,
+                    _ => return Err(format!("Expected table or chart, not `{}`", target)),
+                };
+                let format = match arg(1) {
+                    Some(format) => format.parse::<OutputFormat>()?,
+                    None => OutputFormat::Csv,
+                };
+                Command::Export(target, format)
+            }
+            (name, None) => match Action::ALL.iter().find(|(n, _)| *n == name) {
+                Some((_, action)) => Command::Action(*action),
+                None => return Err(format!("Unknown command `{}`", name)),
+            },
+            (name, Some(_)) => return Err(format!("`{}` takes no arguments", name)),
+        };
+        Ok(command)
+    }
+}
+
+/// Every command with its arguments and what it does
+fn commands() -> Vec<Suggestion> {
+    let actions = Action::ALL.iter().map(|(name, action)| {
+        let args = match action {
+            Action::Timeframe => "[1h|24h|7d] ",
+            Action::Theme | Action::Layout => "[name] ",
+            Action::Export => "[table|chart] [csv|json|markdown] ",
+            _ => "",
+        };
+        Suggestion::new(*name, format!("{}{}", args, action.description()))
+    });
+    let extra = EXTRA_COMMANDS
+        .iter()
+        .map(|(name, args, description)| Suggestion::new(*name, format!("{} {}", args, description)));
+    actions.chain(extra).collect()
+}
+
+/// Values for argument number `position` of `command`
+fn arguments(command: &str, position: usize, app: &App) -> Vec<Suggestion> {
+    match (command, position) {
+        ("currency", 0) => {
+            let mut codes: Vec<&str> = app.fiat_rates.currencies().collect();
+            if codes.len() <= 1 {
+                codes = COMMON_CURRENCIES.to_vec();
+            }
+            codes.sort_unstable();
+            codes.into_iter().map(|c| Suggestion::new(c, "")).collect()
+        }
+        ("goto", 0) => app
+            .coins
+            .iter()
+            .map(|coin| Suggestion::new(coin.symbol.to_lowercase(), coin.name.clone()))
+            .collect(),
+        ("sort", 0) => Field::ALL.iter().map(|f| Suggestion::new(f.name(), f.header())).collect(),
+        ("sort", 1) => vec![Suggestion::new("asc", "Smallest first"), Suggestion::new("desc", "Largest first")],
+        ("timeframe", 0) => ["1h", "24h", "7d"].iter().map(|tf| Suggestion::new(*tf, "")).collect(),
+        ("theme", 0) => app.themes.iter().map(|t| Suggestion::new(t.name.clone(), "")).collect(),
+        ("layout", 0) => std::iter::once(Suggestion::new("default", "Built-in layout"))
+            .chain(app.layouts.iter().map(|l| Suggestion::new(l.name.clone(), "")))
+            .collect(),
+        ("export", 0) => [ExportTarget::Table, ExportTarget::Chart]
+            .iter()
+            .map(|t| Suggestion::new(t.label().split(' ').next_back().unwrap_or_default().to_lowercase(), t.label()))
+            .collect(),
+        ("export", 1) => FORMATS
+            .iter()
+            .map(|f| Suggestion::new(format_label(*f).to_lowercase(), ""))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// How well `candidate` matches `query` when the query's characters appear in it in order:
+/// matches at the start, after a separator and in runs score higher, short candidates win ties
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.to_lowercase().chars() {
+        let found = next + candidate[next..].iter().position(|c| *c == q)?;
+        score += match previous {
+            Some(p) if p + 1 == found => 5,
+            _ => 1,
+        };
+        if found == 0 {
+            score += 10;
+        } else if matches!(candidate[found - 1], ' ' | '-' | '_') {
+            score += 3;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score * 10 - candidate.len() as i32)
+}
+
+/// Carry out the commands that only touch the app; actions and currency changes are left
+/// to the event loop, which owns the background requests
+pub fn run(app: &mut App, command: Command) {
+    match command {
+        Command::Timeframe(timeframe) => {
+            app.chart_timeframe = timeframe;
+            app.update_chart_data();
+        }
+        Command::Theme(index) => {
+            app.set_theme(index);
+            let name = app.themes[index].name.clone();
+            app.show_toast(format!("Theme: {}", name), false);
+        }
+        Command::Layout(index) => {
+            app.layout_index = index;
+            let name = app.layout().map_or("default", |l| l.name.as_str()).to_string();
+            app.show_toast(format!("Layout: {}", name), false);
+        }
+        Command::Export(target, format) => {
+            let mut dialog = ExportDialog::new(&app.export_dir);
+            dialog.target = target;
+            dialog.format = format;
+            match crate::export::export(app, &dialog) {
+                Ok(path) => app.show_toast(format!("Exported to {}", path.display()), false),
+                Err(e) => app.show_toast(format!("Export failed: {:#}", e), true),
+            }
+        }
+        Command::Goto(index) => {
+            app.selected_index = index;
+            app.update_scroll_offset(app.visible_rows);
+            app.update_chart_data();
+        }
+        Command::Sort(Field::Rank, false) => app.set_sort(None),
+        Command::Sort(field, descending) => app.set_sort(Some((field, descending))),
+        Command::Action(_) | Command::Currency(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::CoinMarket;
+    use crossterm::event::KeyModifiers;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.coins = serde_json::from_str::<Vec<CoinMarket>>(
+            r#"[
+                {"id": "bitcoin", "symbol": "btc", "name": "Bitcoin"},
+                {"id": "ethereum", "symbol": "eth", "name": "Ethereum"},
+                {"id": "wrapped-bitcoin", "symbol": "wbtc", "name": "Wrapped Bitcoin"}
+            ]"#,
+        )
+        .unwrap();
+        app
+    }
+
+    fn typed(input: &str) -> CommandPalette {
+        CommandPalette {
+            input: input.to_string(),
+            selected: 0,
+        }
+    }
+
+    fn type_keys(palette: &mut CommandPalette, app: &App, keys: &[KeyCode]) -> DialogAction {
+        let mut action = DialogAction::None;
+        for key in keys {
+            action = palette.handle_key(KeyEvent::new(*key, KeyModifiers::NONE), app);
+        }
+        action
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_prefixes_runs_and_word_starts() {
+        assert_eq!(fuzzy_score("xyz", "export"), None);
+        assert_eq!(fuzzy_score("tx", "export"), None);
+        assert!(fuzzy_score("exp", "export") > fuzzy_score("exp", "expand-export"));
+        assert!(fuzzy_score("exp", "export") > fuzzy_score("xpo", "export"));
+        assert!(fuzzy_score("pd", "page-down") > fuzzy_score("pd", "updated"));
+        assert!(fuzzy_score("SO", "sort") > fuzzy_score("so", "history"));
+        // Nothing typed matches everything, shorter first
+        assert!(fuzzy_score("", "up") > fuzzy_score("", "page-down"));
+    }
+
+    #[test]
+    fn suggests_commands_then_arguments() {
+        let app = app();
+        let texts = |input: &str| -> Vec<String> {
+            typed(input).suggestions(&app).into_iter().map(|s| s.text).collect()
+        };
+        assert_eq!(texts("so")[0], "sort");
+        assert_eq!(texts("")[0], "up");
+        assert_eq!(texts("timeframe "), ["1h", "24h", "7d"]);
+        assert_eq!(texts("sort volume "), ["asc", "desc"]);
+        // Coins match on symbol first, then on name
+        assert_eq!(texts("goto bt"), ["btc", "wbtc"]);
+        assert_eq!(texts("goto ether"), ["eth"]);
+        assert!(texts("currency ").contains(&"eur".to_string()));
+        assert!(texts("quit ").is_empty());
+    }
+
+    #[test]
+    fn parses_commands() {
+        let app = app();
+        let command = |input: &str| typed(input).command(&app);
+        assert_eq!(command("quit"), Ok(Command::Action(Action::Quit)));
+        assert_eq!(command("  Page-Down "), Ok(Command::Action(Action::PageDown)));
+        assert_eq!(command("timeframe"), Ok(Command::Action(Action::Timeframe)));
+        assert_eq!(command("timeframe 7d"), Ok(Command::Timeframe(ChartTimeframe::parse("7d").unwrap())));
+        assert_eq!(command("goto ETH"), Ok(Command::Goto(1)));
+        assert_eq!(command("goto wrapped bitcoin"), Ok(Command::Goto(2)));
+        assert_eq!(command("currency EUR"), Ok(Command::Currency("eur".to_string())));
+        assert_eq!(command("sort volume"), Ok(Command::Sort(Field::parse("volume").unwrap(), true)));
+        assert_eq!(command("sort name desc"), Ok(Command::Sort(Field::parse("name").unwrap(), true)));
+        assert_eq!(command("theme light"), Ok(Command::Theme(find_theme(&app.themes, "light").unwrap())));
+        assert_eq!(command("layout default"), Ok(Command::Layout(None)));
+        assert_eq!(command("export chart json"), Ok(Command::Export(ExportTarget::Chart, OutputFormat::Json)));
+        assert_eq!(command("export table"), Ok(Command::Export(ExportTarget::Table, OutputFormat::Csv)));
+    }
+
+    #[test]
+    fn reports_bad_commands() {
+        let app = app();
+        let error = |input: &str| typed(input).command(&app).unwrap_err();
+        assert_eq!(error(""), "No command given");
+        assert_eq!(error("frobnicate"), "Unknown command `frobnicate`");
+        assert_eq!(error("quit now"), "`quit` takes no arguments");
+        assert_eq!(error("sort"), "Usage: sort <field> [asc|desc]");
+        assert_eq!(error("sort price sideways"), "Expected asc or desc, not `sideways`");
+        assert_eq!(error("sort colour"), "Unknown field `colour`");
+        assert_eq!(error("goto"), "Usage: goto <coin>");
+        assert_eq!(error("goto doge"), "Coin `doge` not found");
+        assert_eq!(error("currency"), "Usage: currency <code>");
+        assert_eq!(error("timeframe 2w"), "Unknown timeframe `2w`");
+        assert_eq!(error("theme neon"), "Unknown theme `neon`");
+        assert_eq!(error("layout wide"), "Unknown layout `wide`");
+        assert_eq!(error("export everything"), "Expected table or chart, not `everything`");
+        assert!(error("export table xml").contains("xml"));
+    }
+
+    #[test]
+    fn tab_and_enter_complete_the_word_being_typed() {
+        let app = app();
+        let mut palette = typed("");
+        type_keys(&mut palette, &app, &[KeyCode::Char('s'), KeyCode::Char('o'), KeyCode::Tab]);
+        assert_eq!(palette.input, "sort ");
+        type_keys(&mut palette, &app, &[KeyCode::Char('v'), KeyCode::Char('o'), KeyCode::Tab, KeyCode::Down]);
+        assert_eq!(palette.input, "sort volume ");
+        assert_eq!(type_keys(&mut palette, &app, &[KeyCode::Enter]), DialogAction::Submit);
+        assert_eq!(palette.command(&app), Ok(Command::Sort(Field::parse("volume").unwrap(), true)));
+
+        let mut palette = typed("goto wb");
+        assert_eq!(type_keys(&mut palette, &app, &[KeyCode::Enter]), DialogAction::Submit);
+        assert_eq!(palette.input, "goto wbtc");
+    }
+
+    #[test]
+    fn enter_after_a_space_reports_the_missing_argument() {
+        let app = app();
+        let mut palette = typed("sort ");
+        assert_eq!(type_keys(&mut palette, &app, &[KeyCode::Enter]), DialogAction::Submit);
+        assert_eq!(palette.command(&app), Err("Usage: sort <field> [asc|desc]".to_string()));
+    }
+
+    #[test]
+    fn escape_and_backspace_on_empty_input_close() {
+        let app = app();
+        assert_eq!(type_keys(&mut typed("the"), &app, &[KeyCode::Esc]), DialogAction::Cancel);
+        let mut palette = typed("q");
+        assert_eq!(type_keys(&mut palette, &app, &[KeyCode::Backspace]), DialogAction::None);
+        assert_eq!(type_keys(&mut palette, &app, &[KeyCode::Backspace]), DialogAction::Cancel);
+    }
+}
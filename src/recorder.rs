@@ -0,0 +1,276 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+
+use crate::api::CoinMarket;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshots (
+    ts            INTEGER NOT NULL,
+    vs_currency   TEXT    NOT NULL,
+    coin_id       TEXT    NOT NULL,
+    symbol        TEXT    NOT NULL,
+    price         REAL,
+    market_cap    REAL,
+    total_volume  REAL,
+    change_1h     REAL,
+    change_24h    REAL,
+    change_7d     REAL,
+    change_14d    REAL,
+    change_30d    REAL,
+    change_60d    REAL,
+    change_200d   REAL,
+    change_1y     REAL,
+    resolution    INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS snapshots_coin_ts ON snapshots (coin_id, vs_currency, ts);
+";
+
+// Resolution of compacted rows; raw rows use 0
+const HOURLY: i64 = 3600;
+
+/// Recorder settings from the `[recorder]` config section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RecorderConfig {
+    pub enabled: bool,
+    pub path: Option<PathBuf>,
+    /// Days to keep every snapshot before compacting to hourly averages
+    pub raw_retention_days: u32,
+    /// Days to keep any history at all
+    pub retention_days: u32,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            raw_retention_days: 7,
+            retention_days: 365,
+        }
+    }
+}
+
+/// Appends each refresh to a local SQLite database
+pub struct Recorder {
+    conn: Connection,
+    config: RecorderConfig,
+    last_compaction: Option<DateTime<Utc>>,
+}
+
+impl Recorder {
+    /// Default database location, e.g. `~/.local/share/coins/history.sqlite`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("coins").join("history.sqlite"))
+    }
+
+    pub fn open(config: RecorderConfig) -> Result<Self> {
+        let path = config
+            .path
+            .clone()
+            .or_else(Self::default_path)
+            .context("No data directory for the history database")?;
+        Self::open_at(&path, config)
+    }
+
+    pub fn open_at(path: &Path, config: RecorderConfig) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        conn.execute_batch(SCHEMA)?;
+
+        let mut recorder = Self {
+            conn,
+            config,
+            last_compaction: None,
+        };
+        recorder.compact()?;
+        Ok(recorder)
+    }
+
+    /// Store one snapshot of every coin, compacting old rows at most once an hour
+    pub fn record(&mut self, vs_currency: &str, coins: &[CoinMarket]) -> Result<()> {
+        let ts = Utc::now().timestamp();
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO snapshots (ts, vs_currency, coin_id, symbol, price, market_cap, total_volume,
+                    change_1h, change_24h, change_7d, change_14d, change_30d, change_60d, change_200d, change_1y)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )?;
+            for coin in coins {
+                insert.execute(params![
+                    ts,
+                    vs_currency,
+                    coin.id,
+                    coin.symbol,
+                    coin.current_price,
+                    coin.market_cap,
+                    coin.total_volume,
+                    coin.price_change_percentage_1h_in_currency,
+                    coin.price_change_percentage_24h_in_currency,
+                    coin.price_change_percentage_7d_in_currency,
+                    coin.price_change_percentage_14d_in_currency,
+                    coin.price_change_percentage_30d_in_currency,
+                    coin.price_change_percentage_60d_in_currency,
+                    coin.price_change_percentage_200d_in_currency,
+                    coin.price_change_percentage_1y_in_currency,
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        if self.last_compaction.is_none_or(|last| Utc::now() - last >= Duration::hours(1)) {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Recorded prices for a coin since `since`, oldest first
+    pub fn price_history(
+        &self,
+        coin_id: &str,
+        vs_currency: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, f64)>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT ts, price FROM snapshots
+             WHERE coin_id = ?1 AND vs_currency = ?2 AND ts >= ?3 AND price IS NOT NULL
+             ORDER BY ts",
+        )?;
+        let rows = query.query_map(params![coin_id, vs_currency, since.timestamp()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?))
+        })?;
+
+        let mut history = Vec::new();
+        for row in rows {
+            let (ts, price) = row?;
+            if let Some(time) = DateTime::from_timestamp(ts, 0) {
+                history.push((time, price));
+            }
+        }
+        Ok(history)
+    }
+
+    /// Apply the retention policy: raw rows older than `raw_retention_days` are
+    /// averaged into one row per coin and hour, and anything older than
+    /// `retention_days` is deleted.
+    pub fn compact(&mut self) -> Result<()> {
+        let now = Utc::now();
+        // Align to the hour so a bucket is never split across two compactions
+        let raw_cutoff = (now - Duration::days(self.config.raw_retention_days.into())).timestamp() / HOURLY * HOURLY;
+        let cutoff = (now - Duration::days(self.config.retention_days.into())).timestamp();
+
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM snapshots WHERE ts < ?1", params![cutoff])?;
+        tx.execute(
+            "INSERT INTO snapshots (ts, vs_currency, coin_id, symbol, price, market_cap, total_volume,
+                change_1h, change_24h, change_7d, change_14d, change_30d, change_60d, change_200d, change_1y, resolution)
+             SELECT (ts / ?2) * ?2, vs_currency, coin_id, MAX(symbol), AVG(price), AVG(market_cap), AVG(total_volume),
+                AVG(change_1h), AVG(change_24h), AVG(change_7d), AVG(change_14d), AVG(change_30d),
+                AVG(change_60d), AVG(change_200d), AVG(change_1y), ?2
+             FROM snapshots
+             WHERE resolution = 0 AND ts < ?1
+             GROUP BY ts / ?2, vs_currency, coin_id",
+            params![raw_cutoff, HOURLY],
+        )?;
+        tx.execute(
+            "DELETE FROM snapshots WHERE resolution = 0 AND ts < ?1",
+            params![raw_cutoff],
+        )?;
+        tx.commit()?;
+
+        self.last_compaction = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder() -> Recorder {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        Recorder {
+            conn,
+            config: RecorderConfig {
+                raw_retention_days: 1,
+                retention_days: 30,
+                ..Default::default()
+            },
+            last_compaction: None,
+        }
+    }
+
+    fn insert(recorder: &Recorder, ts: i64, coin_id: &str, price: f64) {
+        recorder
+            .conn
+            .execute(
+                "INSERT INTO snapshots (ts, vs_currency, coin_id, symbol, price) VALUES (?1, 'usd', ?2, ?2, ?3)",
+                params![ts, coin_id, price],
+            )
+            .unwrap();
+    }
+
+    fn rows(recorder: &Recorder) -> Vec<(i64, String, f64, i64)> {
+        let mut query = recorder
+            .conn
+            .prepare("SELECT ts, coin_id, price, resolution FROM snapshots ORDER BY coin_id, ts")
+            .unwrap();
+        query
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn compaction_averages_old_rows_per_hour() {
+        let mut recorder = recorder();
+        let now = Utc::now().timestamp();
+        let hour = (now - Duration::days(2).num_seconds()) / HOURLY * HOURLY;
+        insert(&recorder, hour + 60, "bitcoin", 100.0);
+        insert(&recorder, hour + 1800, "bitcoin", 200.0);
+        insert(&recorder, hour + HOURLY + 60, "bitcoin", 400.0);
+        insert(&recorder, hour + 120, "ethereum", 10.0);
+        insert(&recorder, now, "bitcoin", 500.0);
+
+        recorder.compact().unwrap();
+        assert_eq!(
+            rows(&recorder),
+            [
+                (hour, "bitcoin".to_string(), 150.0, HOURLY),
+                (hour + HOURLY, "bitcoin".to_string(), 400.0, HOURLY),
+                (now, "bitcoin".to_string(), 500.0, 0),
+                (hour, "ethereum".to_string(), 10.0, HOURLY),
+            ]
+        );
+
+        // Compacted rows are left alone the next time
+        recorder.compact().unwrap();
+        assert_eq!(rows(&recorder).len(), 4);
+    }
+
+    #[test]
+    fn compaction_deletes_rows_past_retention() {
+        let mut recorder = recorder();
+        let now = Utc::now().timestamp();
+        let day = Duration::days(1).num_seconds();
+        insert(&recorder, now - 31 * day, "bitcoin", 1.0);
+        insert(&recorder, now - 29 * day, "bitcoin", 2.0);
+
+        recorder.compact().unwrap();
+        let rows = rows(&recorder);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].2, 2.0);
+        assert!(recorder.last_compaction.is_some());
+    }
+}
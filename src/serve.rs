@@ -0,0 +1,459 @@
+//! `coins serve`: Prometheus metrics and a JSON API over markets polled in the background
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::api::{find_coin, CoinGeckoClient, CoinMarket};
+use crate::chart::{sparkline_series, ChartTimeframe};
+use crate::field::{sort_coins, Field};
+use crate::metrics;
+use crate::output::{json_string, write_coin, write_coins, write_series, OutputFormat};
+
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Latest markets per quote currency, written by the poller and read by requests
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub markets: BTreeMap<String, Vec<CoinMarket>>,
+    pub updated: BTreeMap<String, DateTime<Utc>>,
+}
+
+/// What `coins serve` polls and where it listens
+#[derive(Debug, Clone)]
+pub struct ServeConfig {
+    pub currencies: Vec<String>,
+    /// Keep only the first N coins of each currency
+    pub top: Option<usize>,
+    pub interval: Duration,
+    pub addr: String,
+}
+
+struct State {
+    client: CoinGeckoClient,
+    /// Served currencies; the first one is the default for API requests
+    currencies: Vec<String>,
+    snapshot: RwLock<Snapshot>,
+}
+
+/// Poll CoinGecko in the background and answer HTTP requests until the process is stopped
+pub async fn run(client: CoinGeckoClient, config: ServeConfig) -> io::Result<()> {
+    let listener = TcpListener::bind(&config.addr).await?;
+    let addr = listener.local_addr()?;
+    eprintln!("Serving metrics on http://{}/metrics and the JSON API on http://{}/api", addr, addr);
+
+    let state = Arc::new(State {
+        client,
+        currencies: config.currencies.clone(),
+        snapshot: RwLock::new(Snapshot::default()),
+    });
+    tokio::spawn(poll(Arc::clone(&state), config));
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            // A client hanging up early only affects its own connection
+            let _ = handle(stream, &state).await;
+        });
+    }
+}
+
+async fn poll(state: Arc<State>, config: ServeConfig) {
+    loop {
+        for currency in &config.currencies {
+            match state.client.get_markets(currency).await {
+                Ok(mut coins) => {
+                    if let Some(top) = config.top {
+                        coins.truncate(top);
+                    }
+                    let mut snapshot = state.snapshot.write().unwrap();
+                    snapshot.markets.insert(currency.clone(), coins);
+                    snapshot.updated.insert(currency.clone(), Utc::now());
+                }
+                Err(e) => eprintln!(
+                    "{}  Error fetching {} markets: {}",
+                    Local::now().format("%Y-%m-%d %H:%M:%S"),
+                    currency.to_uppercase(),
+                    e
+                ),
+            }
+        }
+        tokio::time::sleep(config.interval).await;
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn text(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", body),
+        }
+    }
+
+    fn json(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, format!("{{\"error\":{}}}\n", json_string(message)))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Read one request, answer it and close the connection
+async fn handle(mut stream: TcpStream, state: &State) -> io::Result<()> {
+    let mut buf = vec![0; MAX_REQUEST_SIZE];
+    let mut len = 0;
+    while !buf[..len].windows(4).any(|w| w == b"\r\n\r\n") && len < buf.len() {
+        let read = tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buf[len..]))
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+        if read == 0 {
+            break;
+        }
+        len += read;
+    }
+
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+    let response = match (request_line.next(), request_line.next()) {
+        (Some(method), Some(target)) => route(state, method, target),
+        _ => Response::text(400, "Bad Request"),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn route(state: &State, method: &str, target: &str) -> Response {
+    if method != "GET" {
+        return Response::text(405, "Only GET is supported");
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = query_params(query);
+    // Decoded after splitting, so an escaped `/` stays part of its segment
+    let segments: Vec<String> = path.trim_matches('/').split('/').map(percent_decode).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match segments.as_slice() {
+        [""] => Response::text(200, "coins server, metrics are at /metrics and the JSON API at /api"),
+        ["metrics"] => Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: metrics::render(&state.snapshot.read().unwrap(), &state.client.fetch_metrics()),
+        },
+        ["api", rest @ ..] => api(state, rest, &params).unwrap_or_else(|e| e),
+        _ => Response::text(404, "Not Found"),
+    }
+}
+
+/// JSON API over the polled markets. Every endpoint takes `currency`, defaulting to the first served one.
+///
+/// - `/api/markets?top=&sort=&order=asc|desc&fields=`
+/// - `/api/coins/{coin}?fields=`
+/// - `/api/coins/{coin}/chart?timeframe=1h|24h|7d`
+/// - `/api/portfolio?holdings=btc:0.5,eth:2`
+fn api(state: &State, segments: &[&str], params: &HashMap<String, String>) -> Result<Response, Response> {
+    let currency = params
+        .get("currency")
+        .map(|c| c.to_lowercase())
+        .unwrap_or_else(|| state.currencies[0].clone());
+    if !state.currencies.contains(&currency) {
+        return Err(Response::error(
+            400,
+            &format!("currency `{}` is not served, add it to --currency", currency),
+        ));
+    }
+    let snapshot = state.snapshot.read().unwrap();
+    let coins = snapshot
+        .markets
+        .get(&currency)
+        .ok_or_else(|| Response::error(503, "no data fetched yet, try again shortly"))?;
+    let fields = match params.get("fields") {
+        Some(list) => Field::parse_list(list).map_err(|e| Response::error(400, &e))?,
+        None => Field::ALL.to_vec(),
+    };
+    let find = |query: &str| {
+        find_coin(coins, query).ok_or_else(|| Response::error(404, &format!("coin not found: {}", query)))
+    };
+
+    let mut body = Vec::new();
+    match segments {
+        ["markets"] => {
+            let mut coins = coins.clone();
+            if let Some(sort) = params.get("sort") {
+                let field = Field::parse(sort)
+                    .ok_or_else(|| Response::error(400, &format!("unknown sort field `{}`", sort)))?;
+                let descending = match params.get("order").map(String::as_str) {
+                    Some("asc") => false,
+                    Some("desc") => true,
+                    Some(order) => return Err(Response::error(400, &format!("unknown order `{}`", order))),
+                    None => field.default_descending(),
+                };
+                sort_coins(&mut coins, field, descending);
+            }
+            if let Some(top) = params.get("top") {
+                let top = top
+                    .parse()
+                    .map_err(|_| Response::error(400, &format!("invalid top `{}`", top)))?;
+                coins.truncate(top);
+            }
+            write_coins(&mut body, &coins, &fields, OutputFormat::Json, &currency)
+        }
+        ["coins", coin] => write_coin(&mut body, find(coin)?, &fields, OutputFormat::Json, &currency),
+        ["coins", coin, "chart"] => {
+            let label = params.get("timeframe").map_or("7d", String::as_str);
+            let timeframe = ChartTimeframe::parse(label)
+                .ok_or_else(|| Response::error(400, &format!("unknown timeframe `{}`, expected 1h, 24h or 7d", label)))?;
+            let points = sparkline_series(find(coin)?, timeframe);
+            write_series(&mut body, &points, OutputFormat::Json, &currency)
+        }
+        ["portfolio"] => {
+            let holdings = params
+                .get("holdings")
+                .ok_or_else(|| Response::error(400, "holdings are required, e.g. holdings=btc:0.5,eth:2"))?;
+            return portfolio(coins, holdings, &currency).map(|body| Response::json(200, body));
+        }
+        _ => return Err(Response::error(404, "unknown endpoint")),
+    }
+    .map_err(|e| Response::error(500, &e.to_string()))?;
+
+    Ok(Response::json(200, String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Value `holdings` (`coin:amount` pairs) at current prices
+fn portfolio(coins: &[CoinMarket], holdings: &str, currency: &str) -> Result<String, Response> {
+    let mut total = 0.0;
+    let mut rows = Vec::new();
+    for holding in holdings.split(',').filter(|h| !h.is_empty()) {
+        let invalid = || Response::error(400, &format!("invalid holding `{}`, expected coin:amount", holding));
+        let (query, amount) = holding.split_once(':').ok_or_else(invalid)?;
+        let amount: f64 = amount.parse().ok().filter(|a: &f64| a.is_finite()).ok_or_else(invalid)?;
+        let coin = find_coin(coins, query)
+            .ok_or_else(|| Response::error(404, &format!("coin not found: {}", query)))?;
+        let price = coin
+            .current_price
+            .ok_or_else(|| Response::error(404, &format!("no price for {}", query)))?;
+        total += amount * price;
+
+        let mut row = String::new();
+        let _ = write!(
+            row,
+            "{{\"id\":{},\"symbol\":{},\"amount\":{},\"price\":{},\"value\":{},\"change_24h\":{}}}",
+            json_string(&coin.id),
+            json_string(&coin.symbol),
+            amount,
+            price,
+            amount * price,
+            coin.price_change_percentage_24h_in_currency.map_or("null".to_string(), |c| c.to_string())
+        );
+        rows.push(row);
+    }
+
+    Ok(format!(
+        "{{\"currency\":{},\"total\":{},\"holdings\":[{}]}}\n",
+        json_string(currency),
+        total,
+        rows.join(",")
+    ))
+}
+
+/// Parse `a=1&b=2`, decoding `%XX` escapes and `+`
+fn query_params(query: &str) -> HashMap<String, String> {
+    let decode = |s: &str| percent_decode(&s.replace('+', " "));
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn coins() -> Vec<CoinMarket> {
+        serde_json::from_str(
+            r#"[
+                {"id": "bitcoin", "symbol": "btc", "name": "Bitcoin", "current_price": 50000.0,
+                 "market_cap": 1000.0, "price_change_percentage_24h": -1.0,
+                 "price_change_percentage_24h_in_currency": 2.5},
+                {"id": "ethereum", "symbol": "eth", "name": "Ethereum", "current_price": 2000.0,
+                 "market_cap": 500.0},
+                {"id": "wrapped-bitcoin", "symbol": "wbtc", "name": "Wrapped Bitcoin", "current_price": 49900.0,
+                 "market_cap": 10.0}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn state(fetched: bool) -> State {
+        let mut snapshot = Snapshot::default();
+        if fetched {
+            snapshot.markets.insert("usd".to_string(), coins());
+            snapshot.updated.insert("usd".to_string(), Utc::now());
+        }
+        State {
+            client: CoinGeckoClient::new().offline(true),
+            currencies: vec!["usd".to_string(), "eur".to_string()],
+            snapshot: RwLock::new(snapshot),
+        }
+    }
+
+    fn get(state: &State, target: &str) -> (u16, Value) {
+        let response = route(state, "GET", target);
+        let body = serde_json::from_str(&response.body).unwrap_or(Value::String(response.body));
+        (response.status, body)
+    }
+
+    #[test]
+    fn routes_by_method_and_path() {
+        let state = state(true);
+        assert_eq!(route(&state, "POST", "/api/markets").status, 405);
+        assert_eq!(route(&state, "GET", "/").status, 200);
+        assert_eq!(route(&state, "GET", "/nope").status, 404);
+        assert_eq!(get(&state, "/api/nope").0, 404);
+        let metrics = route(&state, "GET", "/metrics");
+        assert_eq!(metrics.status, 200);
+        assert!(metrics.body.contains("coins_price{"));
+    }
+
+    #[test]
+    fn markets_sort_and_limit() {
+        let state = state(true);
+        let (status, body) = get(&state, "/api/markets?sort=price&order=asc&top=2&fields=id,price");
+        assert_eq!(status, 200);
+        let ids: Vec<&str> = body.as_array().unwrap().iter().map(|c| c["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["ethereum", "wrapped-bitcoin"]);
+
+        assert_eq!(get(&state, "/api/markets?sort=colour").0, 400);
+        assert_eq!(get(&state, "/api/markets?order=up&sort=price").0, 400);
+        assert_eq!(get(&state, "/api/markets?top=many").0, 400);
+        assert_eq!(get(&state, "/api/markets?fields=nope").0, 400);
+    }
+
+    #[test]
+    fn currency_must_be_served_and_fetched() {
+        let (status, body) = get(&state(true), "/api/markets?currency=jpy");
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("jpy"));
+        assert_eq!(get(&state(true), "/api/markets?currency=EUR").0, 503);
+        assert_eq!(get(&state(false), "/api/markets").0, 503);
+    }
+
+    #[test]
+    fn coin_paths_are_decoded() {
+        let state = state(true);
+        let (status, body) = get(&state, "/api/coins/Wrapped%20Bitcoin?fields=id");
+        assert_eq!(status, 200);
+        assert_eq!(body["id"], "wrapped-bitcoin");
+        assert_eq!(get(&state, "/api/coins/dogecoin").0, 404);
+        assert_eq!(get(&state, "/api/coins/btc/chart?timeframe=24h").0, 200);
+        assert_eq!(get(&state, "/api/coins/btc/chart?timeframe=2w").0, 400);
+    }
+
+    #[test]
+    fn portfolio_values_holdings() {
+        let state = state(true);
+        let (status, body) = get(&state, "/api/portfolio?holdings=btc:0.5,ethereum:2");
+        assert_eq!(status, 200);
+        assert_eq!(body["currency"], "usd");
+        assert_eq!(body["total"], 29000.0);
+        assert_eq!(body["holdings"][0]["value"], 25000.0);
+        assert_eq!(body["holdings"][0]["change_24h"], 2.5);
+        assert_eq!(body["holdings"][1]["change_24h"], Value::Null);
+
+        assert_eq!(get(&state, "/api/portfolio").0, 400);
+        assert_eq!(get(&state, "/api/portfolio?holdings=btc").0, 400);
+        assert_eq!(get(&state, "/api/portfolio?holdings=btc:lots").0, 400);
+        assert_eq!(get(&state, "/api/portfolio?holdings=btc:inf").0, 400);
+        assert_eq!(get(&state, "/api/portfolio?holdings=doge:1").0, 404);
+    }
+
+    #[test]
+    fn parses_query_params() {
+        let params = query_params("a=1&b=x%20y+z&&c&d=%26%3D");
+        assert_eq!(params["a"], "1");
+        assert_eq!(params["b"], "x y z");
+        assert_eq!(params["c"], "");
+        assert_eq!(params["d"], "&=");
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn percent_decodes_escapes_only() {
+        assert_eq!(percent_decode("%41%62c"), "Abc");
+        assert_eq!(percent_decode("%E2%82%AC"), "\u{20ac}");
+        assert_eq!(percent_decode("a+b"), "a+b");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%ff"), "\u{fffd}");
+    }
+}
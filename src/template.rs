@@ -0,0 +1,154 @@
+//! Format strings for one-line coin summaries
+
+use crate::api::CoinMarket;
+use crate::field::{Field, FieldValue};
+
+/// Decimals beyond this only print float noise and huge ones allocate huge strings
+const MAX_PRECISION: usize = 18;
+
+/// Format string rendered once per coin, e.g. `{symbol} {price:.0} {change_24h:+.1}%`.
+///
+/// Placeholders name a field and optionally a spec: `+` forces a sign and `.N` sets
+/// the number of decimals, at most 18. Without a spec the field is formatted as in tables.
+/// `{{` and `}}` are literal braces.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Field {
+        field: Field,
+        sign: bool,
+        precision: Option<usize>,
+    },
+}
+
+impl Template {
+    /// Parse a format string, failing on unknown fields, bad specs or unbalanced braces
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(format!("Unclosed `{{` in format `{}`", s)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => return Err(format!("Unmatched `}}` in format `{}`", s)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Fill in the placeholders for `coin`
+    pub fn render(&self, coin: &CoinMarket) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(s) => s.clone(),
+                Part::Field { field, sign, precision } => match (field.value(coin), precision) {
+                    (FieldValue::Number(n), Some(p)) if *sign => format!("{:+.*}", p, n),
+                    (FieldValue::Number(n), Some(p)) => format!("{:.*}", p, n),
+                    (FieldValue::Number(n), None) if *sign => format!("{:+}", n),
+                    (FieldValue::Integer(n), _) if *sign => format!("{:+}", n),
+                    _ => field.display(coin),
+                },
+            })
+            .collect()
+    }
+}
+
+/// `price`, `price:.0` or `change_24h:+.1`
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+    let field = Field::parse(name.trim()).ok_or_else(|| format!("Unknown field `{}` in format", name.trim()))?;
+
+    let sign = spec.starts_with('+');
+    let precision = match spec.trim_start_matches('+') {
+        "" => None,
+        digits => Some(
+            digits
+                .strip_prefix('.')
+                .and_then(|n| n.parse::<usize>().ok())
+                .map(|n| n.min(MAX_PRECISION))
+                .ok_or_else(|| format!("Invalid spec `{}` for `{}`, expected e.g. `.2` or `+.1`", spec, name.trim()))?,
+        ),
+    };
+
+    Ok(Part::Field { field, sign, precision })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coin() -> CoinMarket {
+        serde_json::from_str(
+            r#"{"id": "bitcoin", "symbol": "btc", "name": "Bitcoin", "current_price": 67000.125,
+                "market_cap_rank": 1, "price_change_percentage_24h_in_currency": 2.5}"#,
+        )
+        .unwrap()
+    }
+
+    fn render(format: &str) -> String {
+        Template::parse(format).unwrap().render(&coin())
+    }
+
+    #[test]
+    fn renders_fields_and_specs() {
+        assert_eq!(render("{symbol} {price:.0} {change_24h:+.1}%"), "BTC 67000 +2.5%");
+        assert_eq!(render("{change_24h:+}"), "+2.5");
+        assert_eq!(render("#{rank:+}"), "#+1");
+        assert_eq!(render("{ name }"), "Bitcoin");
+    }
+
+    #[test]
+    fn clamps_precision() {
+        assert_eq!(render("{price:.18}"), format!("{:.18}", 67000.125));
+        assert_eq!(render("{price:.1000000}"), render("{price:.18}"));
+        assert!(Template::parse("{price:.99999999999999999999999}").is_err());
+    }
+
+    #[test]
+    fn escaped_braces_are_literal() {
+        assert_eq!(render("{{{symbol}}}"), "{BTC}");
+        assert_eq!(render("}}{{"), "}{");
+    }
+
+    #[test]
+    fn rejects_bad_formats() {
+        assert_eq!(Template::parse("{colour}").unwrap_err(), "Unknown field `colour` in format");
+        assert!(Template::parse("{price:2}").is_err());
+        assert!(Template::parse("{price:.x}").is_err());
+        assert!(Template::parse("{price").unwrap_err().starts_with("Unclosed `{`"));
+        assert!(Template::parse("price}").unwrap_err().starts_with("Unmatched `}`"));
+    }
+}
@@ -0,0 +1,404 @@
+//! Color themes: built-in palettes, custom themes from the config file and color depth fallback
+
+use std::collections::BTreeMap;
+use std::env;
+
+use anyhow::{bail, Context, Result};
+use ratatui::style::{Color, Modifier};
+use serde::Deserialize;
+
+use crate::widgets::WidgetStyle;
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ColorDepth {
+    #[serde(rename = "truecolor")]
+    TrueColor,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    Ansi16,
+    /// No colors at all, as requested by `NO_COLOR`
+    #[serde(rename = "none")]
+    None,
+}
+
+impl ColorDepth {
+    /// Guess from the environment: `NO_COLOR`, then `COLORTERM`, then `TERM`
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        if !var("NO_COLOR").is_empty() {
+            return ColorDepth::None;
+        }
+
+        let colorterm = var("COLORTERM").to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorDepth::TrueColor;
+        }
+        let term = var("TERM");
+        if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else if term == "dumb" {
+            ColorDepth::None
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+/// Colors of price increases and decreases
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeColors {
+    /// The theme's colors, green up and red down in the built-in themes
+    #[default]
+    Standard,
+    /// Blue up and orange down, which stay apart with red-green color blindness
+    Colorblind,
+    /// Red up and green down, as in East Asian markets
+    RedUp,
+}
+
+/// A named set of colors for the TUI
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub style: WidgetStyle,
+}
+
+impl Theme {
+    fn new(name: &str, style: WidgetStyle) -> Self {
+        Self {
+            name: name.to_string(),
+            style,
+        }
+    }
+
+    /// Themes that are always available, the first one is the default
+    pub fn builtin() -> Vec<Theme> {
+        vec![
+            Theme::new("dark", WidgetStyle::DEFAULT),
+            Theme::new(
+                "light",
+                WidgetStyle {
+                    border: Color::Rgb(90, 120, 160),
+                    header: Color::Rgb(0, 90, 170),
+                    positive: Color::Rgb(0, 135, 60),
+                    negative: Color::Rgb(200, 30, 50),
+                    selected_bg: Color::Rgb(210, 225, 240),
+                    selected_modifier: Modifier::empty(),
+                    text: Color::Rgb(40, 40, 40),
+                    muted: Color::Rgb(110, 110, 110),
+                    chart: Color::Rgb(0, 90, 170),
+                    change_signs: false,
+                },
+            ),
+            Theme::new(
+                "solarized",
+                WidgetStyle {
+                    border: Color::Rgb(38, 139, 210),
+                    header: Color::Rgb(42, 161, 152),
+                    positive: Color::Rgb(133, 153, 0),
+                    negative: Color::Rgb(220, 50, 47),
+                    selected_bg: Color::Rgb(7, 54, 66),
+                    selected_modifier: Modifier::empty(),
+                    text: Color::Rgb(147, 161, 161),
+                    muted: Color::Rgb(88, 110, 117),
+                    chart: Color::Rgb(181, 137, 0),
+                    change_signs: false,
+                },
+            ),
+            Theme::new(
+                "high-contrast",
+                WidgetStyle {
+                    border: Color::White,
+                    header: Color::LightYellow,
+                    positive: Color::LightGreen,
+                    negative: Color::LightRed,
+                    selected_bg: Color::Blue,
+                    selected_modifier: Modifier::BOLD,
+                    text: Color::White,
+                    muted: Color::Gray,
+                    chart: Color::LightCyan,
+                    change_signs: false,
+                },
+            ),
+            Theme::new("monochrome", monochrome()),
+        ]
+    }
+
+    /// Colors to draw with on a terminal with `depth` colors, using `change_colors` for ups and downs
+    pub fn style_for(&self, depth: ColorDepth, change_colors: ChangeColors) -> WidgetStyle {
+        if depth == ColorDepth::None {
+            return monochrome();
+        }
+
+        let fit = |color| fit_color(color, depth);
+        let mut style = self.style;
+        match change_colors {
+            // Themes that don't color changes, like monochrome, keep it that way
+            _ if style.positive == style.negative => {}
+            ChangeColors::Standard => {}
+            ChangeColors::Colorblind => {
+                style.positive = Color::Rgb(30, 144, 255);
+                style.negative = Color::Rgb(255, 140, 0);
+            }
+            ChangeColors::RedUp => {
+                (style.positive, style.negative) = (style.negative, style.positive);
+            }
+        }
+        // Dark selection colors fall to black on 16-color terminals, which doesn't show against a
+        // dark background, so the selection is drawn in reverse video instead
+        let (selected_bg, selected_modifier) = match fit(style.selected_bg) {
+            Color::Black | Color::Reset if depth == ColorDepth::Ansi16 => {
+                (Color::Reset, style.selected_modifier | Modifier::REVERSED)
+            }
+            bg => (bg, style.selected_modifier),
+        };
+        WidgetStyle {
+            border: fit(style.border),
+            header: fit(style.header),
+            positive: fit(style.positive),
+            negative: fit(style.negative),
+            selected_bg,
+            selected_modifier,
+            text: fit(style.text),
+            muted: fit(style.muted),
+            chart: fit(style.chart),
+            change_signs: style.change_signs,
+        }
+    }
+}
+
+/// Terminal default colors only, with the selection shown in reverse video
+fn monochrome() -> WidgetStyle {
+    WidgetStyle {
+        border: Color::Reset,
+        header: Color::Reset,
+        positive: Color::Reset,
+        negative: Color::Reset,
+        selected_bg: Color::Reset,
+        selected_modifier: Modifier::REVERSED,
+        text: Color::Reset,
+        muted: Color::Reset,
+        chart: Color::Reset,
+        change_signs: false,
+    }
+}
+
+/// A custom theme from a `[themes.<name>]` config section; unset colors come from `base`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Built-in or earlier custom theme to start from, defaults to `dark`
+    pub base: Option<String>,
+    pub border: Option<String>,
+    pub header: Option<String>,
+    pub positive: Option<String>,
+    pub negative: Option<String>,
+    pub selected_bg: Option<String>,
+    pub text: Option<String>,
+    pub muted: Option<String>,
+    pub chart: Option<String>,
+}
+
+/// Built-in themes followed by the custom ones; a custom theme with a built-in name replaces it
+pub fn load_themes(custom: &BTreeMap<String, ThemeConfig>) -> Result<Vec<Theme>> {
+    let mut themes = Theme::builtin();
+    for (name, config) in custom {
+        let base_name = config.base.as_deref().unwrap_or("dark");
+        let Some(base) = find_theme(&themes, base_name) else {
+            bail!("Theme `{}` is based on unknown theme `{}`", name, base_name);
+        };
+
+        let mut style = themes[base].style;
+        for (value, target) in [
+            (&config.border, &mut style.border),
+            (&config.header, &mut style.header),
+            (&config.positive, &mut style.positive),
+            (&config.negative, &mut style.negative),
+            (&config.selected_bg, &mut style.selected_bg),
+            (&config.text, &mut style.text),
+            (&config.muted, &mut style.muted),
+            (&config.chart, &mut style.chart),
+        ] {
+            if let Some(value) = value {
+                *target = value
+                    .parse()
+                    .with_context(|| format!("Invalid color `{}` in theme `{}`", value, name))?;
+            }
+        }
+
+        match find_theme(&themes, name) {
+            Some(i) => themes[i].style = style,
+            None => themes.push(Theme::new(name, style)),
+        }
+    }
+    Ok(themes)
+}
+
+/// Index of the theme called `name`, ignoring case
+pub fn find_theme(themes: &[Theme], name: &str) -> Option<usize> {
+    themes.iter().position(|t| t.name.eq_ignore_ascii_case(name))
+}
+
+/// Closest color the terminal can show
+fn fit_color(color: Color, depth: ColorDepth) -> Color {
+    match (depth, color) {
+        (ColorDepth::None, _) => Color::Reset,
+        (ColorDepth::TrueColor, _) => color,
+        (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(rgb_to_256(r, g, b)),
+        (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi16((r, g, b)),
+        (ColorDepth::Ansi16, Color::Indexed(i)) if i >= 16 => nearest_ansi16(indexed_to_rgb(i)),
+        (ColorDepth::Ansi16, Color::Indexed(i)) => ANSI16[i as usize].0,
+        _ => color,
+    }
+}
+
+/// The 16 ANSI colors with their usual xterm values
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Channel levels of the 6x6x6 color cube in the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, value)| distance(rgb, *value))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+/// Nearest entry of the color cube or the grayscale ramp
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (v as i32 - **level as i32).abs())
+            .map(|(i, _)| i as u8)
+            .unwrap_or(0)
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + ((average.saturating_sub(3)) / 10).min(23) as u8;
+
+    if distance((r, g, b), indexed_to_rgb(gray)) < distance((r, g, b), indexed_to_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn indexed_to_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI16[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let v = 8 + 10 * (i - 232);
+            (v, v, v)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin(name: &str) -> Theme {
+        Theme::builtin().into_iter().find(|t| t.name == name).unwrap()
+    }
+
+    #[test]
+    fn rgb_to_256_picks_the_cube_or_the_gray_ramp() {
+        assert_eq!(rgb_to_256(0, 0, 0), 16);
+        assert_eq!(rgb_to_256(255, 255, 255), 231);
+        assert_eq!(rgb_to_256(255, 0, 0), 196);
+        assert_eq!(rgb_to_256(0, 135, 255), 33);
+        // Grays land on the finer grayscale ramp rather than the cube
+        assert_eq!(rgb_to_256(128, 128, 128), 244);
+        assert_eq!(rgb_to_256(30, 30, 30), 234);
+    }
+
+    #[test]
+    fn nearest_ansi16_by_distance() {
+        assert_eq!(nearest_ansi16((0, 0, 0)), Color::Black);
+        assert_eq!(nearest_ansi16((30, 50, 70)), Color::Black);
+        assert_eq!(nearest_ansi16((200, 10, 10)), Color::Red);
+        assert_eq!(nearest_ansi16((250, 20, 20)), Color::LightRed);
+        assert_eq!(nearest_ansi16((120, 120, 130)), Color::DarkGray);
+        assert_eq!(fit_color(Color::Indexed(196), ColorDepth::Ansi16), Color::LightRed);
+        assert_eq!(fit_color(Color::Indexed(4), ColorDepth::Ansi16), Color::Blue);
+        assert_eq!(fit_color(Color::Green, ColorDepth::Ansi16), Color::Green);
+    }
+
+    #[test]
+    fn fit_color_keeps_what_the_terminal_can_show() {
+        let rgb = Color::Rgb(100, 200, 255);
+        assert_eq!(fit_color(rgb, ColorDepth::TrueColor), rgb);
+        assert_eq!(fit_color(rgb, ColorDepth::Ansi256), Color::Indexed(81));
+        assert_eq!(fit_color(rgb, ColorDepth::None), Color::Reset);
+    }
+
+    #[test]
+    fn selection_stays_visible_on_16_colors() {
+        for name in ["dark", "solarized"] {
+            let style = builtin(name).style_for(ColorDepth::Ansi16, ChangeColors::Standard);
+            assert!(style.selected_modifier.contains(Modifier::REVERSED), "{}", name);
+            assert_eq!(style.selected_bg, Color::Reset);
+        }
+        // A light selection color still fits to a visible ANSI color
+        let style = builtin("light").style_for(ColorDepth::Ansi16, ChangeColors::Standard);
+        assert_ne!(style.selected_bg, Color::Black);
+        // With more colors the theme's own selection color is kept
+        let style = builtin("dark").style_for(ColorDepth::TrueColor, ChangeColors::Standard);
+        assert_eq!(style.selected_bg, WidgetStyle::DEFAULT.selected_bg);
+        assert!(style.selected_modifier.is_empty());
+    }
+
+    #[test]
+    fn no_color_turns_colors_off() {
+        let saved = env::var_os("NO_COLOR");
+        env::set_var("NO_COLOR", "1");
+        let depth = ColorDepth::detect();
+        match saved {
+            Some(value) => env::set_var("NO_COLOR", value),
+            None => env::remove_var("NO_COLOR"),
+        }
+        assert_eq!(depth, ColorDepth::None);
+
+        let style = builtin("dark").style_for(depth, ChangeColors::Colorblind);
+        assert_eq!((style.positive, style.negative, style.text), (Color::Reset, Color::Reset, Color::Reset));
+        assert!(style.selected_modifier.contains(Modifier::REVERSED));
+    }
+}
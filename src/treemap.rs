@@ -0,0 +1,126 @@
+//! Squarified treemap layout, as used by the market heatmap
+
+/// A rectangle with fractional coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreemapRect {
+    /// Left edge
+    pub x: f64,
+    /// Top edge
+    pub y: f64,
+    /// Width
+    pub width: f64,
+    /// Height
+    pub height: f64,
+}
+
+/// Split `area` into one tile per weight, with areas proportional to the weights and
+/// tiles as close to square as possible (Bruls, Huizing and van Wijk's squarified layout).
+///
+/// Weights should be positive and sorted largest first; tiles are returned in the same order.
+pub fn squarify(weights: &[f64], area: TreemapRect) -> Vec<TreemapRect> {
+    let total: f64 = weights.iter().sum();
+    if weights.is_empty() || total <= 0.0 {
+        return Vec::new();
+    }
+    let scale = area.width * area.height / total;
+    let areas: Vec<f64> = weights.iter().map(|w| w * scale).collect();
+
+    let mut tiles = Vec::with_capacity(areas.len());
+    let mut remaining = area;
+    let mut start = 0;
+    while start < areas.len() {
+        // Grow the row while that makes its worst aspect ratio better
+        let side = remaining.width.min(remaining.height);
+        let mut end = start + 1;
+        while end < areas.len() && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side) {
+            end += 1;
+        }
+
+        // Lay the row along the shorter side of what's left
+        let row = &areas[start..end];
+        let row_area: f64 = row.iter().sum();
+        if remaining.width >= remaining.height {
+            let thickness = if remaining.height > 0.0 { row_area / remaining.height } else { 0.0 };
+            let mut y = remaining.y;
+            for a in row {
+                let height = if thickness > 0.0 { a / thickness } else { 0.0 };
+                tiles.push(TreemapRect { x: remaining.x, y, width: thickness, height });
+                y += height;
+            }
+            remaining.x += thickness;
+            remaining.width -= thickness;
+        } else {
+            let thickness = if remaining.width > 0.0 { row_area / remaining.width } else { 0.0 };
+            let mut x = remaining.x;
+            for a in row {
+                let width = if thickness > 0.0 { a / thickness } else { 0.0 };
+                tiles.push(TreemapRect { x, y: remaining.y, width, height: thickness });
+                x += width;
+            }
+            remaining.y += thickness;
+            remaining.height -= thickness;
+        }
+        start = end;
+    }
+    tiles
+}
+
+/// Largest aspect ratio in a row of `areas` laid along a side of length `side`
+fn worst_ratio(areas: &[f64], side: f64) -> f64 {
+    let sum: f64 = areas.iter().sum();
+    let (side2, sum2) = (side * side, sum * sum);
+    areas
+        .iter()
+        .map(|&a| (side2 * a / sum2).max(sum2 / (side2 * a)))
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: TreemapRect = TreemapRect { x: 2.0, y: 1.0, width: 80.0, height: 30.0 };
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn tiles_cover_the_area_exactly() {
+        let tiles = squarify(&[50.0, 20.0, 12.0, 8.0, 5.0, 3.0, 1.0, 1.0], AREA);
+        assert_eq!(tiles.len(), 8);
+
+        let covered: f64 = tiles.iter().map(|t| t.width * t.height).sum();
+        assert!(close(covered, AREA.width * AREA.height));
+        for (i, a) in tiles.iter().enumerate() {
+            assert!(a.x >= AREA.x - 1e-9 && a.x + a.width <= AREA.x + AREA.width + 1e-9, "{:?}", a);
+            assert!(a.y >= AREA.y - 1e-9 && a.y + a.height <= AREA.y + AREA.height + 1e-9, "{:?}", a);
+            for b in &tiles[i + 1..] {
+                let overlap_x = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+                let overlap_y = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+                assert!(overlap_x <= 1e-9 || overlap_y <= 1e-9, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn tile_area_is_proportional_to_weight() {
+        let weights = [6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0];
+        let total: f64 = weights.iter().sum();
+        let tiles = squarify(&weights, AREA);
+        for (weight, tile) in weights.iter().zip(&tiles) {
+            assert!(close(tile.width * tile.height, weight / total * AREA.width * AREA.height));
+        }
+    }
+
+    #[test]
+    fn single_weight_fills_the_area() {
+        assert_eq!(squarify(&[3.0], AREA), [AREA]);
+    }
+
+    #[test]
+    fn nothing_to_lay_out() {
+        assert!(squarify(&[], AREA).is_empty());
+        assert!(squarify(&[0.0, 0.0], AREA).is_empty());
+    }
+}
@@ -1,521 +1,689 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    symbols,
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{
-        Axis, Block, Borders, Cell, Chart, Dataset, GraphType, Paragraph, Row, Table,
-    },
+    widgets::{Block, Borders, Clear, Paragraph, Tabs},
     Frame,
 };
 
-use crate::app::App;
+use crate::api::ApiError;
+use crate::app::{App, ChartSource, CompactTab, View};
+use crate::convert;
+use crate::converter::ConverterDialog;
+use crate::export::{format_label, ExportDialog};
+use crate::format::{format_amount, format_price};
+use crate::keymap::Action;
+use crate::layout::Panel;
+use crate::palette::CommandPalette;
+use crate::widgets::{
+    ChangesPanel, CoinTable, CoinTableState, DetailsPanel, Heatmap, HeatmapState, PriceChart, PricePanel,
+    SupplyPanel, WidgetStyle,
+};
+
+/// Below this width or height the coin list and the info panels share the screen as tabs
+const COMPACT_WIDTH: u16 = 100;
+const COMPACT_HEIGHT: u16 = 28;
+/// From this size on the chart grows and the info panels get two columns of their own
+const WIDE_WIDTH: u16 = 180;
+const WIDE_HEIGHT: u16 = 45;
+/// Height the info panels need stacked in one column
+const INFO_STACKED_HEIGHT: u16 = 33;
+
+/// Size classes the layout adapts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayoutMode {
+    /// Chart, then either the coin list or the info panels
+    Compact,
+    /// Chart, then the coin list next to the info panels
+    Regular,
+    /// Taller chart, then the coin list next to two columns of info panels
+    Wide,
+}
 
-// Color scheme (matching the reference image)
-const BORDER_COLOR: Color = Color::Rgb(70, 130, 180);
-const HEADER_COLOR: Color = Color::Rgb(100, 200, 255);
-const POSITIVE_COLOR: Color = Color::Rgb(0, 255, 127);
-const NEGATIVE_COLOR: Color = Color::Rgb(255, 69, 100);
-const SELECTED_BG: Color = Color::Rgb(30, 50, 70);
-const TEXT_COLOR: Color = Color::Rgb(200, 200, 200);
-const MUTED_COLOR: Color = Color::Rgb(120, 120, 120);
-const CHART_COLOR: Color = Color::Rgb(100, 200, 255);
+impl LayoutMode {
+    fn for_area(area: Rect) -> Self {
+        if area.width < COMPACT_WIDTH || area.height < COMPACT_HEIGHT {
+            LayoutMode::Compact
+        } else if area.width >= WIDE_WIDTH && area.height >= WIDE_HEIGHT {
+            LayoutMode::Wide
+        } else {
+            LayoutMode::Regular
+        }
+    }
+}
 
-pub fn ui(frame: &mut Frame, app: &App) {
+pub fn ui(frame: &mut Frame, app: &mut App) {
     let size = frame.area();
+    // Only what this frame draws can be clicked
+    app.table = CoinTableState::default();
+    app.heatmap = HeatmapState::default();
+    app.chart_area = None;
+    app.chart_title_area = None;
 
-    // Main layout
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(20), // Price Chart (top)
-            Constraint::Min(10),        // Main content (coin list + info panels)
-            Constraint::Length(3),      // Footer/Help
+            Constraint::Min(0),    // Chart, coin list and info panels
+            Constraint::Length(3), // Footer/Help
         ])
         .split(size);
 
-    render_price_chart(frame, app, chunks[0]);
-    render_main_content(frame, app, chunks[1]);
-    render_footer(frame, app, chunks[2]);
+    match app.layout().map(|layout| layout.place(chunks[0])) {
+        Some(panels) => render_custom_layout(frame, app, &panels),
+        None => render_adaptive_layout(frame, app, chunks[0]),
+    }
+    render_footer(frame, app, chunks[1]);
+
+    if let Some(dialog) = &app.export_dialog {
+        render_export_dialog(frame, dialog, app.style, size);
+    }
+    if let Some(dialog) = &app.converter {
+        render_converter(frame, app, dialog, size);
+    }
+    if let Some(palette) = &app.palette {
+        render_palette(frame, app, palette, size);
+    }
+    if app.show_help {
+        render_help(frame, app, size);
+    }
+    render_toast(frame, app, chunks[1]);
 }
 
-fn render_main_content(frame: &mut Frame, app: &App, area: Rect) {
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50), // Coin list
-            Constraint::Percentage(50), // Info panels
+/// Overlay listing the key bindings in effect, then the mouse controls
+fn render_help(frame: &mut Frame, app: &App, area: Rect) {
+    let style = app.style;
+    let bindings = app.keymap.help();
+    let key_width = bindings.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0).max(12);
+
+    let line = |keys: &str, description: &str| {
+        Line::from(vec![
+            Span::styled(format!(" {:<width$}  ", keys, width = key_width), Style::default().fg(style.header)),
+            Span::styled(description.to_string(), Style::default().fg(style.text)),
         ])
-        .split(area);
+    };
+    let mut lines: Vec<Line> = bindings.iter().map(|(keys, action)| line(keys, action.description())).collect();
+    lines.push(line("Ctrl+C", "Quit"));
+    lines.push(Line::from(""));
+    lines.push(line("Click", "Select a coin, sort by a header, cycle the timeframe from the chart title"));
+    lines.push(line("Scroll", "Move the selection in the coin table"));
+    lines.push(line("Hover", "Show the chart price at that point"));
+
+    let width = lines.iter().map(|l| l.width() as u16).max().unwrap_or(0) + 3;
+    let popup = centered_rect(width.min(area.width), (lines.len() as u16 + 2).min(area.height), area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(style.header))
+        .title(" Keys (any key to close) ")
+        .title_style(Style::default().fg(style.header).add_modifier(Modifier::BOLD));
 
-    render_coin_table(frame, app, chunks[0]);
-    render_info_panel(frame, app, chunks[1]);
+    frame.render_widget(Clear, popup);
+    frame.render_widget(Paragraph::new(lines).block(block), popup);
 }
 
-fn render_info_panel(frame: &mut Frame, app: &App, area: Rect) {
+/// The built-in layout, which follows the terminal size
+fn render_adaptive_layout(frame: &mut Frame, app: &mut App, area: Rect) {
+    let size = frame.area();
+    let mode = LayoutMode::for_area(size);
+
+    let chart_height = match mode {
+        // Very short terminals give the chart up entirely
+        LayoutMode::Compact if size.height < 24 => Constraint::Length(0),
+        LayoutMode::Compact => Constraint::Length(size.height / 4),
+        LayoutMode::Regular => Constraint::Percentage(20),
+        LayoutMode::Wide => Constraint::Percentage(30),
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(5),  // Price info
-            Constraint::Length(12), // Changes info
-            Constraint::Length(14), // Details info
-            Constraint::Min(6),     // Supply info
+            chart_height,       // Price Chart (top)
+            Constraint::Min(6), // Main content (coin list + info panels)
         ])
         .split(area);
 
-    render_price_info(frame, app, chunks[0]);
-    render_changes_info(frame, app, chunks[1]);
-    render_details_info(frame, app, chunks[2]);
-    render_supply_info(frame, app, chunks[3]);
+    render_price_chart(frame, app, chunks[0]);
+    match mode {
+        LayoutMode::Compact => render_compact_content(frame, app, chunks[1]),
+        LayoutMode::Regular => render_main_content(frame, app, chunks[1], 50, false),
+        LayoutMode::Wide => render_main_content(frame, app, chunks[1], 40, true),
+    }
 }
 
-fn render_coin_table(frame: &mut Frame, app: &App, area: Rect) {
-    let header_cells = ["#", "Coin", "Price (USD)", "1h %", "24h %", "7d %", "Market Cap"]
-        .iter()
-        .map(|h| {
-            Cell::from(*h).style(
-                Style::default()
-                    .fg(HEADER_COLOR)
-                    .add_modifier(Modifier::BOLD),
-            )
-        });
-    let header = Row::new(header_cells).height(1);
-
-    let visible_height = area.height.saturating_sub(4) as usize;
-    
-    let rows = app.coins.iter().enumerate().skip(app.scroll_offset).take(visible_height).map(|(i, coin)| {
-        let rank = coin.market_cap_rank.unwrap_or(0).to_string();
-        let symbol = coin.symbol.to_uppercase();
-        let price = format_price(coin.current_price.unwrap_or(0.0));
-        let change_1h = format_percentage(coin.price_change_percentage_1h_in_currency);
-        let change_24h = format_percentage(coin.price_change_percentage_24h_in_currency);
-        let change_7d = format_percentage(coin.price_change_percentage_7d_in_currency);
-        let market_cap = format_large_number(coin.market_cap.unwrap_or(0.0));
-
-        let style = if i == app.selected_index {
-            Style::default().bg(SELECTED_BG)
-        } else {
-            Style::default()
-        };
+/// A layout from the config file
+fn render_custom_layout(frame: &mut Frame, app: &mut App, panels: &[(Panel, Rect)]) {
+    for &(panel, area) in panels {
+        let coin = app.selected_coin();
+        match panel {
+            Panel::Chart => render_price_chart(frame, app, area),
+            Panel::Table => render_coin_list(frame, app, area),
+            Panel::Price => {
+                frame.render_widget(PricePanel::new(coin).currency(&app.vs_currency).style(app.style), area)
+            }
+            Panel::Changes => frame.render_widget(ChangesPanel::new(coin).style(app.style), area),
+            Panel::Details => {
+                frame.render_widget(DetailsPanel::new(coin).currency(&app.vs_currency).style(app.style), area)
+            }
+            Panel::Supply => frame.render_widget(SupplyPanel::new(coin).style(app.style), area),
+        }
+    }
+}
 
-        Row::new(vec![
-            Cell::from(rank).style(Style::default().fg(MUTED_COLOR)),
-            Cell::from(symbol).style(Style::default().fg(TEXT_COLOR)),
-            Cell::from(price).style(Style::default().fg(TEXT_COLOR)),
-            Cell::from(change_1h.0).style(Style::default().fg(change_1h.1)),
-            Cell::from(change_24h.0).style(Style::default().fg(change_24h.1)),
-            Cell::from(change_7d.0).style(Style::default().fg(change_7d.1)),
-            Cell::from(market_cap).style(Style::default().fg(MUTED_COLOR)),
-        ])
-        .style(style)
-        .height(1)
-    });
-
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Length(4),
-            Constraint::Length(8),
-            Constraint::Length(14),
-            Constraint::Length(9),
-            Constraint::Length(9),
-            Constraint::Length(9),
-            Constraint::Min(12),
-        ],
+/// Rect of `width` x `height` centered in `area`
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
     )
-    .header(header)
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(BORDER_COLOR))
-            .title(format!(" Top 100 Coins by Market Cap ({}/{}) ", app.selected_index + 1, app.coins.len()))
-            .title_style(Style::default().fg(HEADER_COLOR)),
-    );
-
-    frame.render_widget(table, area);
 }
 
-fn render_price_info(frame: &mut Frame, app: &App, area: Rect) {
-    let content = if let Some(coin) = app.selected_coin() {
-        let price = coin.current_price.unwrap_or(0.0);
-        let high = coin.high_24h.unwrap_or(0.0);
-        let low = coin.low_24h.unwrap_or(0.0);
-        let change = coin.price_change_percentage_24h.unwrap_or(0.0);
-        let change_color = if change >= 0.0 { POSITIVE_COLOR } else { NEGATIVE_COLOR };
-        let arrow = if change >= 0.0 { "▲" } else { "▼" };
-
-        vec![
-            Line::from(vec![
-                Span::styled(
-                    format!("{} ", coin.symbol.to_uppercase()),
-                    Style::default().fg(HEADER_COLOR).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    &coin.name,
-                    Style::default().fg(TEXT_COLOR),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled(
-                    format!("${:.2} ", price),
-                    Style::default().fg(TEXT_COLOR).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    format!("{} {:.2}%", arrow, change.abs()),
-                    Style::default().fg(change_color),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled("H: ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(format!("${:.2} ", high), Style::default().fg(POSITIVE_COLOR)),
-                Span::styled("L: ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(format!("${:.2}", low), Style::default().fg(NEGATIVE_COLOR)),
-            ]),
-        ]
-    } else {
-        vec![Line::from("No coin selected")]
+fn render_export_dialog(frame: &mut Frame, dialog: &ExportDialog, style: WidgetStyle, area: Rect) {
+    let area = centered_rect(60, 7, area);
+    let row = |index: usize, label: &str, value: String| {
+        let focused = dialog.focus == index;
+        let value_style = if focused {
+            style.selected().fg(style.text).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(style.text)
+        };
+        Line::from(vec![
+            Span::styled(format!(" {:<10}", label), Style::default().fg(style.muted)),
+            Span::styled(value, value_style),
+        ])
     };
 
+    let content = vec![
+        row(0, "Export", format!("◀ {} ▶", dialog.target.label())),
+        row(1, "Format", format!("◀ {} ▶", format_label(dialog.format))),
+        row(2, "Directory", format!("{}▏", dialog.directory)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(" ↑/↓", Style::default().fg(style.header)),
+            Span::styled(" Field  ", Style::default().fg(style.text)),
+            Span::styled("←/→", Style::default().fg(style.header)),
+            Span::styled(" Change  ", Style::default().fg(style.text)),
+            Span::styled("Enter", Style::default().fg(style.header)),
+            Span::styled(" Export  ", Style::default().fg(style.text)),
+            Span::styled("Esc", Style::default().fg(style.header)),
+            Span::styled(" Cancel", Style::default().fg(style.text)),
+        ]),
+    ];
+
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(BORDER_COLOR))
-        .title(" Live Price (USD) ")
-        .title_style(Style::default().fg(HEADER_COLOR));
+        .border_style(Style::default().fg(style.header))
+        .title(" Export ")
+        .title_style(Style::default().fg(style.header).add_modifier(Modifier::BOLD));
 
-    let paragraph = Paragraph::new(content).block(block);
-    frame.render_widget(paragraph, area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(content).block(block), area);
 }
 
-fn render_changes_info(frame: &mut Frame, app: &App, area: Rect) {
-    let content = if let Some(coin) = app.selected_coin() {
-        let changes = [
-            ("1H", coin.price_change_percentage_1h_in_currency),
-            ("24H", coin.price_change_percentage_24h_in_currency),
-            ("7D", coin.price_change_percentage_7d_in_currency),
-            ("14D", coin.price_change_percentage_14d_in_currency),
-            ("30D", coin.price_change_percentage_30d_in_currency),
-            ("60D", coin.price_change_percentage_60d_in_currency),
-            ("200D", coin.price_change_percentage_200d_in_currency),
-            ("1Y", coin.price_change_percentage_1y_in_currency),
-        ];
-
-        changes
-            .iter()
-            .map(|(label, value)| {
-                let (formatted, color) = format_percentage(*value);
-                Line::from(vec![
-                    Span::styled(format!("{:5} ", label), Style::default().fg(MUTED_COLOR)),
-                    Span::styled(formatted, Style::default().fg(color)),
-                ])
-            })
-            .collect::<Vec<_>>()
-    } else {
-        vec![Line::from("No coin selected")]
+/// Converter popup; the result is recalculated on every draw from the typed fields
+fn render_converter(frame: &mut Frame, app: &App, dialog: &ConverterDialog, area: Rect) {
+    let style = app.style;
+    let area = centered_rect(60, 9, area);
+    let from = app.resolve_unit(&dialog.from);
+    let to = app.resolve_unit(&dialog.to);
+
+    let row = |index: usize, label: &str, value: &str, note: String| {
+        let focused = dialog.focus == index;
+        let value_style = if focused {
+            style.selected().fg(style.text).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(style.text)
+        };
+        let cursor = if focused { "▏" } else { " " };
+        Line::from(vec![
+            Span::styled(format!(" {:<8}", label), Style::default().fg(style.muted)),
+            Span::styled(format!("{}{}", value, cursor), value_style),
+            Span::styled(format!("  {}", note), Style::default().fg(style.muted)),
+        ])
+    };
+    let unit_note = |query: &str, unit: &Option<convert::Unit>| match unit {
+        Some(unit) => unit.name().to_string(),
+        None if query.is_empty() => String::new(),
+        None if !app.fiat_rates.is_loaded() => "loading rates…".to_string(),
+        None => "unknown coin or currency".to_string(),
+    };
+
+    let amount = convert::parse_amount(&dialog.amount);
+    let result = match (amount, &from, &to) {
+        (Some(amount), Some(from), Some(to)) => {
+            match convert::convert(amount, from, to, &app.fiat_rates) {
+                Some(value) => Line::from(vec![
+                    Span::styled(
+                        format!(" {} {} = ", format_amount(amount), from.code()),
+                        Style::default().fg(style.text),
+                    ),
+                    Span::styled(
+                        format!("{} {}", format_amount(value), to.code()),
+                        Style::default().fg(style.positive).add_modifier(Modifier::BOLD),
+                    ),
+                ]),
+                None => Line::from(Span::styled(" No rate available", Style::default().fg(style.negative))),
+            }
+        }
+        (None, _, _) if !dialog.amount.is_empty() => {
+            Line::from(Span::styled(" Invalid amount", Style::default().fg(style.negative)))
+        }
+        _ => Line::from(""),
     };
 
+    let content = vec![
+        row(0, "Amount", &dialog.amount, String::new()),
+        row(1, "From", &dialog.from, unit_note(&dialog.from, &from)),
+        row(2, "To", &dialog.to, unit_note(&dialog.to, &to)),
+        Line::from(""),
+        result,
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(" Tab", Style::default().fg(style.header)),
+            Span::styled(" Field  ", Style::default().fg(style.text)),
+            Span::styled("Ctrl+S", Style::default().fg(style.header)),
+            Span::styled(" Swap  ", Style::default().fg(style.text)),
+            Span::styled("Esc", Style::default().fg(style.header)),
+            Span::styled(" Close", Style::default().fg(style.text)),
+        ]),
+    ];
+
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(BORDER_COLOR))
-        .title(" Changes ")
-        .title_style(Style::default().fg(HEADER_COLOR));
+        .border_style(Style::default().fg(style.header))
+        .title(" Convert ")
+        .title_style(Style::default().fg(style.header).add_modifier(Modifier::BOLD));
 
-    let paragraph = Paragraph::new(content).block(block);
-    frame.render_widget(paragraph, area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(content).block(block), area);
 }
 
-fn render_details_info(frame: &mut Frame, app: &App, area: Rect) {
-    let content = if let Some(coin) = app.selected_coin() {
-        let format_date = |date: &Option<String>| -> String {
-            date.as_ref()
-                .map(|d| d.chars().take(16).collect::<String>().replace("T", " "))
-                .unwrap_or_else(|| "N/A".to_string())
-        };
+/// Command line with the completions for the word being typed below it
+fn render_palette(frame: &mut Frame, app: &App, palette: &CommandPalette, area: Rect) {
+    const MAX_SUGGESTIONS: usize = 8;
+    let style = app.style;
+    let suggestions = palette.suggestions(app);
+    let shown = suggestions.len().clamp(1, MAX_SUGGESTIONS);
+    let popup = centered_rect(70, shown as u16 + 6, area);
 
-        vec![
-            Line::from(vec![
-                Span::styled("Name        ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(&coin.name, Style::default().fg(TEXT_COLOR)),
-            ]),
-            Line::from(vec![
-                Span::styled("Symbol      ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(coin.symbol.to_uppercase(), Style::default().fg(TEXT_COLOR)),
-            ]),
-            Line::from(vec![
-                Span::styled("Rank        ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(
-                    coin.market_cap_rank.map(|r| r.to_string()).unwrap_or_else(|| "N/A".to_string()),
-                    Style::default().fg(HEADER_COLOR),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled("MarketCap   ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(
-                    format!("{} USD $", format_large_number(coin.market_cap.unwrap_or(0.0))),
-                    Style::default().fg(TEXT_COLOR),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled("ATH         ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(
-                    format!("{} USD $", format_large_number(coin.ath.unwrap_or(0.0))),
-                    Style::default().fg(POSITIVE_COLOR),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled("ATHDate     ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(format_date(&coin.ath_date), Style::default().fg(MUTED_COLOR)),
-            ]),
-            Line::from(vec![
-                Span::styled("ATL         ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(
-                    format!("{} USD $", format_price(coin.atl.unwrap_or(0.0))),
-                    Style::default().fg(NEGATIVE_COLOR),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled("ATLDate     ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(format_date(&coin.atl_date), Style::default().fg(MUTED_COLOR)),
-            ]),
-            Line::from(vec![
-                Span::styled("TotalVolume ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(
-                    format!("{} USD $", format_large_number(coin.total_volume.unwrap_or(0.0))),
-                    Style::default().fg(TEXT_COLOR),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled("LastUpdate  ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(format_date(&coin.last_updated), Style::default().fg(MUTED_COLOR)),
-            ]),
-        ]
-    } else {
-        vec![Line::from("No coin selected")]
-    };
+    let mut content = vec![
+        Line::from(vec![
+            Span::styled(" :", Style::default().fg(style.header).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{}▏", palette.input), Style::default().fg(style.text)),
+        ]),
+        Line::from(""),
+    ];
+    // Scroll the list along with the highlighted suggestion
+    let first = palette.selected.saturating_sub(MAX_SUGGESTIONS - 1);
+    let text_width = suggestions.iter().map(|s| s.text.chars().count()).max().unwrap_or(0).min(24);
+    for (i, suggestion) in suggestions.iter().enumerate().skip(first).take(MAX_SUGGESTIONS) {
+        let (text_style, detail_style) = if i == palette.selected {
+            let selected = style.selected().add_modifier(Modifier::BOLD);
+            (selected.fg(style.text), selected.fg(style.muted))
+        } else {
+            (Style::default().fg(style.text), Style::default().fg(style.muted))
+        };
+        content.push(Line::from(vec![
+            Span::styled(format!(" {:<width$}  ", suggestion.text, width = text_width), text_style),
+            Span::styled(suggestion.detail.clone(), detail_style),
+        ]));
+    }
+    if suggestions.is_empty() {
+        content.push(Line::from(Span::styled(" No matches", Style::default().fg(style.muted))));
+    }
+    content.push(Line::from(""));
+    content.push(Line::from(vec![
+        Span::styled(" Tab", Style::default().fg(style.header)),
+        Span::styled(" Complete  ", Style::default().fg(style.text)),
+        Span::styled("↑/↓", Style::default().fg(style.header)),
+        Span::styled(" Choose  ", Style::default().fg(style.text)),
+        Span::styled("Enter", Style::default().fg(style.header)),
+        Span::styled(" Run  ", Style::default().fg(style.text)),
+        Span::styled("Esc", Style::default().fg(style.header)),
+        Span::styled(" Close", Style::default().fg(style.text)),
+    ]));
 
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(BORDER_COLOR))
-        .title(" Details ")
-        .title_style(Style::default().fg(HEADER_COLOR));
+        .border_style(Style::default().fg(style.header))
+        .title(" Command ")
+        .title_style(Style::default().fg(style.header).add_modifier(Modifier::BOLD));
 
-    let paragraph = Paragraph::new(content).block(block);
-    frame.render_widget(paragraph, area);
+    frame.render_widget(Clear, popup);
+    frame.render_widget(Paragraph::new(content).block(block), popup);
 }
 
-fn render_supply_info(frame: &mut Frame, app: &App, area: Rect) {
-    let content = if let Some(coin) = app.selected_coin() {
-        let circulating = coin.circulating_supply.unwrap_or(0.0);
-        let total = coin.total_supply.unwrap_or(0.0);
-        let max = coin.max_supply;
-        
-        // Calculate supply ratio for progress bar
-        let supply_ratio = if let Some(max_supply) = max {
-            if max_supply > 0.0 {
-                (circulating / max_supply * 100.0).min(100.0)
-            } else {
-                0.0
-            }
-        } else if total > 0.0 {
-            (circulating / total * 100.0).min(100.0)
-        } else {
-            0.0
-        };
-
-        // Create visual progress bar
-        let bar_width = 20;
-        let filled = (supply_ratio / 100.0 * bar_width as f64) as usize;
-        let bar = format!(
-            "{}{}",
-            "█".repeat(filled),
-            "░".repeat(bar_width - filled)
-        );
-
-        vec![
-            Line::from(vec![
-                Span::styled("Circulating ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(format_large_number(circulating), Style::default().fg(POSITIVE_COLOR)),
-            ]),
-            Line::from(vec![
-                Span::styled("Total       ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(format_large_number(total), Style::default().fg(TEXT_COLOR)),
-            ]),
-            Line::from(vec![
-                Span::styled("Max Supply  ", Style::default().fg(MUTED_COLOR)),
-                Span::styled(
-                    max.map(format_large_number).unwrap_or_else(|| "∞ Unlimited".to_string()),
-                    Style::default().fg(HEADER_COLOR),
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled(bar, Style::default().fg(CHART_COLOR)),
-                Span::styled(format!(" {:.1}%", supply_ratio), Style::default().fg(TEXT_COLOR)),
-            ]),
-        ]
-    } else {
-        vec![Line::from("No coin selected")]
+/// Draw the toast in the bottom-right corner, just above the footer
+fn render_toast(frame: &mut Frame, app: &App, footer: Rect) {
+    let style = app.style;
+    let Some(toast) = &app.toast else {
+        return;
     };
 
+    let width = (toast.message.chars().count() as u16 + 4).min(footer.width);
+    let area = Rect::new(
+        footer.x + footer.width - width,
+        footer.y.saturating_sub(3),
+        width,
+        3,
+    );
+    let color = if toast.is_error { style.negative } else { style.positive };
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(BORDER_COLOR))
-        .title(" Supply ")
-        .title_style(Style::default().fg(HEADER_COLOR));
+        .border_style(Style::default().fg(color));
 
-    let paragraph = Paragraph::new(content).block(block);
-    frame.render_widget(paragraph, area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Span::styled(&toast.message, Style::default().fg(style.text))).block(block),
+        area,
+    );
 }
 
-fn render_price_chart(frame: &mut Frame, app: &App, area: Rect) {
-    let timeframe_label = app.chart_timeframe.label();
-    
-    // Get the change percentage based on current timeframe
-    let change_info = if let Some(coin) = app.selected_coin() {
-        let change = match app.chart_timeframe {
-            crate::app::ChartTimeframe::OneHour => coin.price_change_percentage_1h_in_currency,
-            crate::app::ChartTimeframe::TwentyFourHours => coin.price_change_percentage_24h_in_currency,
-            crate::app::ChartTimeframe::SevenDays => coin.price_change_percentage_7d_in_currency,
-        };
-        match change {
-            Some(v) if v >= 0.0 => format!(" ▲ {:.2}%", v),
-            Some(v) => format!(" ▼ {:.2}%", v.abs()),
-            None => String::new(),
-        }
-    } else {
-        String::new()
-    };
+/// Coin list (or heatmap) on the left taking `list_percent` of the width, info panels on the right
+fn render_main_content(frame: &mut Frame, app: &mut App, area: Rect, list_percent: u16, info_columns: bool) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(list_percent),       // Coin list
+            Constraint::Percentage(100 - list_percent), // Info panels
+        ])
+        .split(area);
 
-    let chart_title = format!(" Price Chart ({}{}) [T to cycle] ", timeframe_label, change_info);
-    
-    if app.chart_data.is_empty() {
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(BORDER_COLOR))
-            .title(chart_title)
-            .title_style(Style::default().fg(HEADER_COLOR));
-        
-        let msg = if app.loading {
-            "Loading chart data..."
-        } else {
-            "No chart data available"
-        };
-        
-        let paragraph = Paragraph::new(msg)
-            .style(Style::default().fg(MUTED_COLOR))
-            .block(block);
-        
-        frame.render_widget(paragraph, area);
+    render_coin_list(frame, app, chunks[0]);
+    render_info_panel(frame, app, chunks[1], info_columns);
+}
+
+/// Tab bar above either the coin list or the info panels
+fn render_compact_content(frame: &mut Frame, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(area);
+
+    let style = app.style;
+    let tabs = Tabs::new([" Coins ", " Details "])
+        .select(match app.compact_tab {
+            CompactTab::Coins => 0,
+            CompactTab::Details => 1,
+        })
+        .style(Style::default().fg(style.muted))
+        .highlight_style(Style::default().fg(style.header).add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let tab_bar = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(5), Constraint::Min(0)])
+        .split(chunks[0]);
+    frame.render_widget(Paragraph::new(Span::styled(" Tab ", Style::default().fg(style.header))), tab_bar[0]);
+    frame.render_widget(tabs, tab_bar[1]);
+
+    match app.compact_tab {
+        CompactTab::Coins => render_coin_list(frame, app, chunks[1]),
+        CompactTab::Details => render_info_panel(frame, app, chunks[1], false),
+    }
+}
+
+fn render_coin_list(frame: &mut Frame, app: &mut App, area: Rect) {
+    match app.view {
+        View::Table => render_coin_table(frame, app, area),
+        View::Heatmap => render_heatmap(frame, app, area),
+    }
+}
+
+/// The four info panels in two columns, or stacked when there's room for that
+fn render_info_panel(frame: &mut Frame, app: &App, area: Rect, columns: bool) {
+    let coin = app.selected_coin();
+    let price = PricePanel::new(coin).currency(&app.vs_currency).style(app.style);
+    let changes = ChangesPanel::new(coin).style(app.style);
+    let details = DetailsPanel::new(coin).currency(&app.vs_currency).style(app.style);
+    let supply = SupplyPanel::new(coin).style(app.style);
+
+    let columns = columns || area.height < INFO_STACKED_HEIGHT;
+    if !columns || area.width < 60 {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(5),  // Price info
+                Constraint::Length(10), // Changes info
+                Constraint::Length(12), // Details info
+                Constraint::Min(6),     // Supply info
+            ])
+            .split(area);
+        frame.render_widget(price, chunks[0]);
+        frame.render_widget(changes, chunks[1]);
+        frame.render_widget(details, chunks[2]);
+        frame.render_widget(supply, chunks[3]);
         return;
     }
 
-    let data: Vec<(f64, f64)> = app.chart_data
-        .iter()
-        .enumerate()
-        .map(|(i, &price)| (i as f64, price))
-        .collect();
-
-    let min_price = app.chart_data.iter().cloned().fold(f64::INFINITY, f64::min);
-    let max_price = app.chart_data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-    let price_range = max_price - min_price;
-    let padding = price_range * 0.1;
-
-    let datasets = vec![Dataset::default()
-        .marker(symbols::Marker::Braille)
-        .graph_type(GraphType::Line)
-        .style(Style::default().fg(CHART_COLOR))
-        .data(&data)];
-
-    let chart = Chart::new(datasets)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(BORDER_COLOR))
-                .title(chart_title)
-                .title_style(Style::default().fg(HEADER_COLOR)),
-        )
-        .x_axis(
-            Axis::default()
-                .style(Style::default().fg(MUTED_COLOR))
-                .bounds([0.0, data.len() as f64]),
-        )
-        .y_axis(
-            Axis::default()
-                .style(Style::default().fg(MUTED_COLOR))
-                .bounds([min_price - padding, max_price + padding])
-                .labels(vec![
-                    Span::raw(format!("{:.0}", min_price)),
-                    Span::raw(format!("{:.0}", max_price)),
-                ]),
-        );
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(5), Constraint::Min(10)])
+        .split(columns[0]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(12), Constraint::Min(6)])
+        .split(columns[1]);
+    frame.render_widget(price, left[0]);
+    frame.render_widget(changes, left[1]);
+    frame.render_widget(details, right[0]);
+    frame.render_widget(supply, right[1]);
+}
 
+fn render_coin_table(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.table.selected = app.selected_index;
+    app.table.offset = app.scroll_offset;
+    let table = CoinTable::new(&app.coins)
+        .columns(&app.columns)
+        .sparkline(app.sparkline)
+        .sort(app.sort)
+        .currency(&app.vs_currency)
+        .title(format!(
+            " Top 100 Coins by Market Cap ({}/{}) ",
+            app.selected_index + 1,
+            app.coins.len()
+        ))
+        .style(app.style);
+
+    frame.render_stateful_widget(table, area, &mut app.table);
+    // Paging in the key handlers goes by what actually fits
+    app.visible_rows = app.table.visible_rows.max(1);
+    app.scroll_offset = app.table.offset;
+}
+
+fn render_heatmap(frame: &mut Frame, app: &mut App, area: Rect) {
+    let heatmap = Heatmap::new(&app.coins)
+        .timeframe(app.chart_timeframe)
+        .title(format!(
+            " Market Heatmap ({}) {}",
+            app.chart_timeframe.label(),
+            key_hint(app, Action::Heatmap, "for table")
+        ))
+        .style(app.style);
+
+    app.heatmap.selected = app.selected_index;
+    frame.render_stateful_widget(heatmap, area, &mut app.heatmap);
+}
+
+/// `[<key> <what>] ` for the first key bound to `action`, or nothing when it has none
+fn key_hint(app: &App, action: Action, what: &str) -> String {
+    app.keymap
+        .keys(action)
+        .first()
+        .map(|keys| format!("[{} {}] ", keys, what))
+        .unwrap_or_default()
+}
+
+fn render_price_chart(frame: &mut Frame, app: &mut App, area: Rect) {
+    // Change over the current timeframe, shown next to its label
+    let change_info = app
+        .selected_coin()
+        .and_then(|coin| app.chart_timeframe.change(coin))
+        .map(|v| format!(" {}", app.style.percentage(Some(v))))
+        .unwrap_or_default();
+
+    let source_label = match app.chart_source {
+        ChartSource::Sparkline => "",
+        ChartSource::Recorded => " · Recorded",
+    };
+    let chart_title = format!(
+        " Price Chart ({}{}){} {}",
+        app.chart_timeframe.label(),
+        change_info,
+        source_label,
+        key_hint(app, Action::Timeframe, "to cycle")
+    );
+    // Price under the mouse, after the part of the title that cycles the timeframe
+    let hover_info = app
+        .chart_hover
+        .and_then(|i| Some((app.chart_data.get(i)?, app.chart_times.get(i)?)))
+        .map(|(price, time)| {
+            format!(
+                " {} {} at {} ",
+                format_price(*price),
+                app.vs_currency.to_uppercase(),
+                time.with_timezone(&chrono::Local).format("%m-%d %H:%M")
+            )
+        })
+        .unwrap_or_default();
+
+    let empty_message = if app.loading {
+        "Loading chart data..."
+    } else if app.chart_source == ChartSource::Recorded {
+        "No recorded history yet for this timeframe"
+    } else {
+        "No chart data available"
+    };
+
+    let title_width = chart_title.chars().count() as u16;
+    let chart = PriceChart::new(&app.chart_data)
+        .title(Line::from(vec![
+            Span::raw(chart_title),
+            Span::styled(hover_info, Style::default().fg(app.style.header)),
+        ]))
+        .highlight(app.chart_hover)
+        .empty_message(empty_message)
+        .style(app.style);
     frame.render_widget(chart, area);
+
+    app.chart_area = Some(area);
+    app.chart_title_area = Some(Rect::new(area.x + 1, area.y, title_width, 1).intersection(area));
 }
 
 fn render_footer(frame: &mut Frame, app: &App, area: Rect) {
+    let style = app.style;
+    let stale_since = app
+        .stale_since
+        .map(|since| since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"));
+
     let update_status = if app.loading {
-        "Loading...".to_string()
+        match &stale_since {
+            Some(since) => format!("Refreshing, showing data from {}", since),
+            None => "Loading...".to_string(),
+        }
+    } else if let Some(wait) = app.rate_limit.cooldown {
+        format!("Rate limited, retry in {}s", wait.as_secs() + 1)
+    } else if let Some(since) = &stale_since {
+        if app.offline {
+            format!("Offline, cached {}", since)
+        } else {
+            format!("Stale since {}", since)
+        }
     } else if let Some(last) = app.last_update {
         let elapsed = last.elapsed().as_secs();
         format!("Updated {}s ago", elapsed)
     } else {
         "Not updated".to_string()
     };
+    let status_color = if app.rate_limit.cooldown.is_some() {
+        style.negative
+    } else if app.stale_since.is_some() {
+        style.header
+    } else {
+        style.muted
+    };
 
-    let help = vec![
-        Line::from(vec![
-            Span::styled(" ↑/k", Style::default().fg(HEADER_COLOR)),
-            Span::styled(" Up  ", Style::default().fg(TEXT_COLOR)),
-            Span::styled("↓/j", Style::default().fg(HEADER_COLOR)),
-            Span::styled(" Down  ", Style::default().fg(TEXT_COLOR)),
-            Span::styled("PgUp/PgDn", Style::default().fg(HEADER_COLOR)),
-            Span::styled(" Page  ", Style::default().fg(TEXT_COLOR)),
-            Span::styled("g/G", Style::default().fg(HEADER_COLOR)),
-            Span::styled(" Top/Bottom  ", Style::default().fg(TEXT_COLOR)),
-            Span::styled("T", Style::default().fg(HEADER_COLOR)),
-            Span::styled(" Timeframe  ", Style::default().fg(TEXT_COLOR)),
-            Span::styled("r", Style::default().fg(HEADER_COLOR)),
-            Span::styled(" Refresh  ", Style::default().fg(TEXT_COLOR)),
-            Span::styled("q", Style::default().fg(HEADER_COLOR)),
-            Span::styled(" Quit  ", Style::default().fg(TEXT_COLOR)),
-            Span::styled(&update_status, Style::default().fg(MUTED_COLOR)),
-        ]),
+    let status = vec![
+        Span::styled(update_status, Style::default().fg(status_color)),
+        Span::styled(
+            format!("  {} API {}/{}", app.api_plan.label(), app.rate_limit.used, app.rate_limit.budget),
+            Style::default().fg(style.muted),
+        ),
     ];
 
-    let block = Block::default()
+    // Bindings from the keymap, as many as fit next to the status
+    let status_width: usize = status.iter().map(|span| span.width()).sum();
+    let mut room = (area.width.saturating_sub(2) as usize).saturating_sub(status_width + 1);
+    let mut spans = vec![Span::raw(" ")];
+    if !app.pending_keys.is_empty() {
+        let typed: Vec<String> = app.pending_keys.iter().map(|key| key.to_string()).collect();
+        let pending = format!("{} …  ", typed.join(" "));
+        room = room.saturating_sub(pending.chars().count());
+        spans.push(Span::styled(pending, Style::default().fg(style.header)));
+    }
+    for action in Action::FOOTER {
+        let Some(keys) = app.keymap.keys(action).first().map(|keys| keys.to_string()) else {
+            continue;
+        };
+        let label = format!(" {}  ", action.label());
+        let width = keys.chars().count() + label.chars().count();
+        if width > room {
+            break;
+        }
+        room -= width;
+        spans.push(Span::styled(keys, Style::default().fg(style.header)));
+        spans.push(Span::styled(label, Style::default().fg(style.text)));
+    }
+    spans.extend(status);
+    let help = vec![Line::from(spans)];
+
+    let mut block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(BORDER_COLOR));
+        .border_style(Style::default().fg(style.border));
+    if let Some(error) = error_title(app) {
+        block = block.title(error);
+    } else if let Some(error) = &app.recorder_error {
+        block = block.title(Span::styled(
+            format!(" ⚠ {} ", error),
+            Style::default().fg(style.negative),
+        ));
+    }
 
     let paragraph = Paragraph::new(help).block(block);
     frame.render_widget(paragraph, area);
 }
 
-fn format_price(price: f64) -> String {
-    if price >= 1.0 {
-        format!("{:.2}", price)
-    } else if price >= 0.01 {
-        format!("{:.4}", price)
-    } else {
-        format!("{:.8}", price)
-    }
-}
+fn error_title(app: &App) -> Option<Line<'static>> {
+    let style = app.style;
+    let error = app.error.as_ref()?;
+    let (icon, message) = match error {
+        ApiError::Network(_) => ("🔌", "Network error".to_string()),
+        ApiError::Timeout(after) => ("⏱", format!("Request timed out after {}s", after.as_secs())),
+        ApiError::RateLimited { .. } => match app.rate_limit.cooldown {
+            Some(wait) => ("⏳", format!("Rate limited, retrying in {}s", wait.as_secs() + 1)),
+            None => ("⏳", "Rate limited".to_string()),
+        },
+        ApiError::Status { status, .. } => ("⚠", format!("CoinGecko returned HTTP {}", status)),
+        ApiError::Decode { field, .. } => ("🧩", format!("Could not read field `{}`", field)),
+        ApiError::InvalidCurrency(currency) => ("💱", format!("Unknown currency {}", currency.to_uppercase())),
+        ApiError::NotCached => ("📦", "No cached data".to_string()),
+        other => ("⚠", other.to_string()),
+    };
 
-fn format_percentage(value: Option<f64>) -> (String, Color) {
-    match value {
-        Some(v) if v >= 0.0 => (format!("▲ {:.2}%", v), POSITIVE_COLOR),
-        Some(v) => (format!("▼ {:.2}%", v.abs()), NEGATIVE_COLOR),
-        None => ("N/A".to_string(), MUTED_COLOR),
-    }
-}
+    // The API can't know the key bindings, so name the refresh key here
+    let hint = match (error, app.keymap.keys(Action::Refresh).first()) {
+        (ApiError::Timeout(_), Some(keys)) => format!("CoinGecko is slow to respond, press {} to retry", keys),
+        _ => error.hint().to_string(),
+    };
 
-fn format_large_number(n: f64) -> String {
-    if n >= 1_000_000_000_000.0 {
-        format!("{:.2}T", n / 1_000_000_000_000.0)
-    } else if n >= 1_000_000_000.0 {
-        format!("{:.2}B", n / 1_000_000_000.0)
-    } else if n >= 1_000_000.0 {
-        format!("{:.2}M", n / 1_000_000.0)
-    } else if n >= 1_000.0 {
-        format!("{:.2}K", n / 1_000.0)
-    } else {
-        format!("{:.2}", n)
-    }
+    Some(Line::from(vec![
+        Span::styled(
+            format!(" {} {} ", icon, message),
+            Style::default().fg(style.negative).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!("- {} ", hint), Style::default().fg(style.muted)),
+    ]))
 }
@@ -0,0 +1,1227 @@
+//! Ratatui widgets for coin data that can be embedded in any TUI.
+//!
+//! Each widget takes plain data and a [`WidgetStyle`], draws its own bordered block and
+//! knows nothing about the `coins` application state:
+//!
+//! ```no_run
+//! # use ratatui::{buffer::Buffer, layout::Rect, widgets::{StatefulWidget, Widget}};
+//! # use coins::api::CoinMarket;
+//! use coins::widgets::{CoinTable, CoinTableState, PriceChart, WidgetStyle};
+//!
+//! # fn draw(coins: &[CoinMarket], area: Rect, buf: &mut Buffer) {
+//! let style = WidgetStyle::default();
+//! let mut state = CoinTableState::default();
+//! CoinTable::new(coins).style(style).render(area, buf, &mut state);
+//!
+//! let prices: Vec<f64> = coins[0].sparkline_in_7d.as_ref().map(|s| s.price.clone()).unwrap_or_default();
+//! PriceChart::new(&prices).title(" BTC 7D ").style(style).render(area, buf);
+//! # }
+//! ```
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Position, Rect},
+    style::{Color, Modifier, Style},
+    symbols,
+    text::{Line, Span},
+    widgets::{Axis, Block, Borders, Cell, Chart, Dataset, GraphType, Paragraph, Row, StatefulWidget, Table, Widget},
+};
+use serde::Deserialize;
+
+use crate::api::CoinMarket;
+use crate::chart::{sparkline_blocks, sparkline_braille, ChartTimeframe};
+use crate::field::{Field, FieldValue};
+use crate::format::{format_large_number, format_percentage, format_percentage_signed, format_price};
+use crate::treemap::{squarify, TreemapRect};
+
+/// Colors used by the widgets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidgetStyle {
+    /// Block borders
+    pub border: Color,
+    /// Titles, headers and highlighted values
+    pub header: Color,
+    /// Price increases and highs
+    pub positive: Color,
+    /// Price decreases and lows
+    pub negative: Color,
+    /// Background of the selected table row
+    pub selected_bg: Color,
+    /// Added to the selected row, e.g. `REVERSED` for themes without colors
+    pub selected_modifier: Modifier,
+    /// Regular text
+    pub text: Color,
+    /// Labels and secondary values
+    pub muted: Color,
+    /// Chart line and supply bar
+    pub chart: Color,
+    /// Show changes as `▲ +1.23%` / `▼ −1.23%` / `● 0.00%` instead of `▲ 1.23%` / `▼ 1.23%`
+    pub change_signs: bool,
+}
+
+impl WidgetStyle {
+    /// The default dark color scheme
+    pub const DEFAULT: WidgetStyle = WidgetStyle {
+        border: Color::Rgb(70, 130, 180),
+        header: Color::Rgb(100, 200, 255),
+        positive: Color::Rgb(0, 255, 127),
+        negative: Color::Rgb(255, 69, 100),
+        selected_bg: Color::Rgb(30, 50, 70),
+        selected_modifier: Modifier::empty(),
+        text: Color::Rgb(200, 200, 200),
+        muted: Color::Rgb(120, 120, 120),
+        chart: Color::Rgb(100, 200, 255),
+        change_signs: false,
+    };
+
+    /// Color for a percentage change: positive, negative or muted when missing
+    pub fn change_color(&self, value: Option<f64>) -> Color {
+        match value {
+            Some(v) if v >= 0.0 => self.positive,
+            Some(_) => self.negative,
+            None => self.muted,
+        }
+    }
+
+    /// Bordered block with a title, as used by every widget
+    pub fn block<'a>(&self, title: impl Into<Line<'a>>) -> Block<'a> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.border))
+            .title(title)
+            .title_style(Style::default().fg(self.header))
+    }
+
+    /// Formatted percentage change, with signs if `change_signs` is set
+    pub fn percentage(&self, value: Option<f64>) -> String {
+        if self.change_signs {
+            format_percentage_signed(value)
+        } else {
+            format_percentage(value)
+        }
+    }
+
+    /// Style of the selected row or field
+    pub fn selected(&self) -> Style {
+        Style::default().bg(self.selected_bg).add_modifier(self.selected_modifier)
+    }
+
+    fn label(&self, text: &'static str) -> Span<'static> {
+        Span::styled(text, Style::default().fg(self.muted))
+    }
+}
+
+impl Default for WidgetStyle {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Currency sign shown before amounts, if the currency has a well-known one
+fn currency_sign(vs_currency: &str) -> Option<&'static str> {
+    match vs_currency {
+        "usd" => Some("$"),
+        "eur" => Some("€"),
+        "gbp" => Some("£"),
+        "jpy" => Some("¥"),
+        _ => None,
+    }
+}
+
+/// `$1.23` for currencies with a sign, `1.23 CHF` otherwise
+fn money(amount: String, vs_currency: &str) -> String {
+    match currency_sign(vs_currency) {
+        Some(sign) => format!("{}{}", sign, amount),
+        None => format!("{} {}", amount, vs_currency.to_uppercase()),
+    }
+}
+
+fn no_coin<'a>() -> Vec<Line<'a>> {
+    vec![Line::from("No coin selected")]
+}
+
+/// A column of a [`CoinTable`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// A coin field, formatted like the rest of the TUI
+    Field(Field),
+    /// Sparkline of the last 7 days, colored by its direction (`sparkline`)
+    Sparkline,
+}
+
+impl Column {
+    /// Rank, coin, price, 1h/24h/7d change and market cap
+    pub const DEFAULT: [Column; 7] = [
+        Column::Field(Field::Rank),
+        Column::Field(Field::Symbol),
+        Column::Field(Field::Price),
+        Column::Field(Field::Change1h),
+        Column::Field(Field::Change24h),
+        Column::Field(Field::Change7d),
+        Column::Field(Field::MarketCap),
+    ];
+
+    /// Parse a field name or `sparkline`
+    pub fn parse(s: &str) -> Option<Column> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "sparkline" | "sparkline_7d" => Some(Column::Sparkline),
+            _ => Field::parse(s).map(Column::Field),
+        }
+    }
+
+    /// Parse a comma-separated list of columns
+    pub fn parse_list(s: &str) -> Result<Vec<Column>, String> {
+        let columns = s
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| Column::parse(name).ok_or_else(|| format!("Unknown column `{}`", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if columns.is_empty() {
+            return Err("No columns given".to_string());
+        }
+        Ok(columns)
+    }
+
+    /// The field shown, `None` for the sparkline
+    pub fn field(&self) -> Option<Field> {
+        match self {
+            Column::Field(field) => Some(*field),
+            Column::Sparkline => None,
+        }
+    }
+
+    /// Columns with a higher number are hidden first when the table is too narrow
+    pub fn priority(&self) -> u8 {
+        match self {
+            Column::Field(Field::Symbol | Field::Name | Field::Id) => 0,
+            Column::Field(Field::Price) => 1,
+            Column::Field(Field::Change24h) => 2,
+            Column::Field(Field::Rank) => 3,
+            Column::Field(Field::MarketCap) => 4,
+            Column::Field(Field::Change7d) => 5,
+            Column::Sparkline => 6,
+            Column::Field(Field::Change1h) => 7,
+            Column::Field(Field::Volume) => 8,
+            Column::Field(_) => 9,
+        }
+    }
+}
+
+/// How sparklines in a [`CoinTable`] are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SparklineStyle {
+    /// Eight levels of block characters, `▁▃▅█`
+    #[default]
+    #[serde(alias = "block")]
+    Blocks,
+    /// Braille dots, two points per character on four levels
+    Braille,
+}
+
+impl SparklineStyle {
+    /// Parse `block(s)` or `braille`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "block" | "blocks" => Some(SparklineStyle::Blocks),
+            "braille" => Some(SparklineStyle::Braille),
+            _ => None,
+        }
+    }
+
+    /// Draw `prices` `width` characters wide
+    pub fn render(&self, prices: &[f64], width: usize) -> String {
+        match self {
+            SparklineStyle::Blocks => sparkline_blocks(prices, width),
+            SparklineStyle::Braille => sparkline_braille(prices, width),
+        }
+    }
+}
+
+/// Width of the sparkline column in characters
+const SPARKLINE_WIDTH: u16 = 14;
+
+/// Selection and scroll position of a [`CoinTable`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoinTableState {
+    /// Index of the highlighted coin
+    pub selected: usize,
+    /// Index of the first visible coin
+    pub offset: usize,
+    /// Number of coin rows that fit, set by each render
+    pub visible_rows: usize,
+    /// Area of the whole table and of its coin rows, as drawn by the last render
+    area: Rect,
+    rows: Rect,
+    /// Header cell of every column drawn by the last render
+    headers: Vec<(Column, Rect)>,
+}
+
+impl CoinTableState {
+    /// Area the table was last rendered in
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    /// Index of the coin in the row at `x`/`y`, as drawn by the last render
+    pub fn coin_at(&self, x: u16, y: u16) -> Option<usize> {
+        self.rows
+            .contains(Position::new(x, y))
+            .then(|| self.offset + (y - self.rows.y) as usize)
+    }
+
+    /// Column whose header is at `x`/`y`, as drawn by the last render
+    pub fn header_at(&self, x: u16, y: u16) -> Option<Column> {
+        self.headers
+            .iter()
+            .find(|(_, area)| area.contains(Position::new(x, y)))
+            .map(|(column, _)| *column)
+    }
+}
+
+/// Table of coins with configurable columns, sized to their content.
+/// When the area is too narrow, the columns with the highest [`Column::priority`] are left out.
+#[derive(Debug, Clone)]
+pub struct CoinTable<'a> {
+    coins: &'a [CoinMarket],
+    columns: &'a [Column],
+    sparkline: SparklineStyle,
+    sort: Option<(Field, bool)>,
+    vs_currency: &'a str,
+    title: Option<Line<'a>>,
+    style: WidgetStyle,
+}
+
+impl<'a> CoinTable<'a> {
+    /// Table of `coins` quoted in USD with the [default columns](Column::DEFAULT)
+    pub fn new(coins: &'a [CoinMarket]) -> Self {
+        Self {
+            coins,
+            columns: &Column::DEFAULT,
+            sparkline: SparklineStyle::default(),
+            sort: None,
+            vs_currency: "usd",
+            title: None,
+            style: WidgetStyle::default(),
+        }
+    }
+
+    /// Columns to show, in order
+    pub fn columns(mut self, columns: &'a [Column]) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// How the sparkline column is drawn
+    pub fn sparkline(mut self, sparkline: SparklineStyle) -> Self {
+        self.sparkline = sparkline;
+        self
+    }
+
+    /// Quote currency of the prices, used in the header
+    pub fn currency(mut self, vs_currency: &'a str) -> Self {
+        self.vs_currency = vs_currency;
+        self
+    }
+
+    /// Field and direction (`true` for descending) the coins are sorted by, marked in its header
+    pub fn sort(mut self, sort: Option<(Field, bool)>) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Replace the default `Top N Coins by Market Cap (i/N)` title
+    pub fn title(mut self, title: impl Into<Line<'a>>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Colors of the table
+    pub fn style(mut self, style: WidgetStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn header(&self, column: Column) -> String {
+        let name = match column {
+            Column::Field(Field::Price) => format!("Price ({})", self.vs_currency.to_uppercase()),
+            Column::Field(field) => field.header().to_string(),
+            Column::Sparkline => "7d Trend".to_string(),
+        };
+        // Arrow on the column the coins are sorted by
+        match self.sort {
+            Some((field, descending)) if column.field() == Some(field) => {
+                format!("{} {}", name, if descending { "▼" } else { "▲" })
+            }
+            _ => name,
+        }
+    }
+
+    /// Text of a cell and its color
+    fn cell(&self, column: Column, coin: &CoinMarket) -> (String, Color) {
+        let style = self.style;
+        let Column::Field(field) = column else {
+            let prices = coin.sparkline_in_7d.as_ref().map(|s| s.price.as_slice()).unwrap_or_default();
+            // Colored by the 7 day change, or by the line itself when CoinGecko left that out
+            let change = coin.price_change_percentage_7d_in_currency.or_else(|| {
+                let (first, last) = (prices.first()?, prices.last()?);
+                Some(last - first)
+            });
+            return (self.sparkline.render(prices, SPARKLINE_WIDTH as usize), style.change_color(change));
+        };
+
+        let value = match field.value(coin) {
+            FieldValue::Number(n) => Some(n),
+            _ => None,
+        };
+        match field {
+            Field::Change1h
+            | Field::Change24h
+            | Field::Change7d
+            | Field::Change14d
+            | Field::Change30d
+            | Field::Change60d
+            | Field::Change200d
+            | Field::Change1y
+            | Field::AthChange => (style.percentage(value), style.change_color(value)),
+            Field::Symbol | Field::Name | Field::Price | Field::High24h | Field::Low24h | Field::Ath | Field::Atl => {
+                (field.display(coin), style.text)
+            }
+            _ => (field.display(coin), style.muted),
+        }
+    }
+
+    /// Widest of the header and every cell, so columns don't jump while scrolling
+    fn width(&self, column: Column) -> u16 {
+        let cells = self.coins.iter().map(|coin| match column {
+            Column::Sparkline => SPARKLINE_WIDTH as usize,
+            _ => self.cell(column, coin).0.chars().count(),
+        });
+        cells
+            .chain(std::iter::once(self.header(column).chars().count()))
+            .max()
+            .unwrap_or(0) as u16
+    }
+}
+
+/// Drop the least important columns until the rest fit in `available` with one space between them
+fn fit_columns(mut columns: Vec<(Column, u16)>, available: u16) -> Vec<(Column, u16)> {
+    let total = |columns: &[(Column, u16)]| -> u16 {
+        columns.iter().map(|(_, w)| w).sum::<u16>() + columns.len().saturating_sub(1) as u16
+    };
+    while columns.len() > 1 && total(&columns) > available {
+        // max_by_key returns the last maximum, so among equal priorities the rightmost goes
+        let (drop, _) = columns
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (column, _))| column.priority())
+            .expect("more than one column");
+        columns.remove(drop);
+    }
+    columns
+}
+
+impl StatefulWidget for CoinTable<'_> {
+    type State = CoinTableState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let style = self.style;
+        let columns = fit_columns(
+            self.columns.iter().map(|c| (*c, self.width(*c))).collect(),
+            area.width.saturating_sub(2),
+        );
+
+        let header_style = Style::default().fg(style.header).add_modifier(Modifier::BOLD);
+        let header = Row::new(
+            columns
+                .iter()
+                .map(|(column, _)| Cell::from(self.header(*column)).style(header_style)),
+        )
+        .height(1);
+
+        // Keep the selection visible: borders and header take 3 rows, leave one spare
+        let visible_height = area.height.saturating_sub(4) as usize;
+        state.visible_rows = visible_height;
+        if state.selected < state.offset {
+            state.offset = state.selected;
+        } else if visible_height > 0 && state.selected >= state.offset + visible_height {
+            state.offset = state.selected + 1 - visible_height;
+        }
+
+        let rows = self
+            .coins
+            .iter()
+            .enumerate()
+            .skip(state.offset)
+            .take(visible_height)
+            .map(|(i, coin)| {
+                let row_style = if i == state.selected {
+                    style.selected()
+                } else {
+                    Style::default()
+                };
+                Row::new(columns.iter().map(|(column, _)| {
+                    let (text, color) = self.cell(*column, coin);
+                    Cell::from(text).style(Style::default().fg(color))
+                }))
+                .style(row_style)
+                .height(1)
+            });
+
+        // The last column takes any space left over
+        let widths = columns.iter().enumerate().map(|(i, (_, width))| {
+            if i + 1 == columns.len() {
+                Constraint::Min(*width)
+            } else {
+                Constraint::Length(*width)
+            }
+        });
+
+        // Remember where rows and headers went, for mouse clicks
+        state.area = area;
+        let inner = Rect::new(area.x + 1, area.y + 1, area.width.saturating_sub(2), area.height.saturating_sub(2));
+        let drawn = self.coins.len().saturating_sub(state.offset).min(visible_height);
+        state.rows = Rect::new(inner.x, inner.y + 1, inner.width, drawn as u16).intersection(area);
+        state.headers.clear();
+        let mut x = inner.x;
+        for (i, (column, width)) in columns.iter().enumerate() {
+            let width = if i + 1 == columns.len() { inner.right().saturating_sub(x) } else { *width };
+            state.headers.push((*column, Rect::new(x, inner.y, width, 1).intersection(inner)));
+            x = x.saturating_add(width + 1);
+        }
+
+        let title = self.title.clone().unwrap_or_else(|| {
+            Line::from(format!(
+                " Top {} Coins by Market Cap ({}/{}) ",
+                self.coins.len(),
+                (state.selected + 1).min(self.coins.len()),
+                self.coins.len()
+            ))
+        });
+        let table = Table::new(rows, widths).header(header).block(style.block(title));
+
+        Widget::render(table, area, buf);
+    }
+}
+
+/// Smallest tile, in cells, that the heatmap tries to give every coin it shows
+const MIN_TILE_CELLS: f64 = 8.0;
+
+/// Selection and tile positions of a [`Heatmap`], updated on every render
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeatmapState {
+    /// Index of the highlighted coin
+    pub selected: usize,
+    tiles: Vec<(usize, Rect)>,
+}
+
+impl HeatmapState {
+    /// Coin index and area of every tile drawn by the last render
+    pub fn tiles(&self) -> &[(usize, Rect)] {
+        &self.tiles
+    }
+
+    /// Coin index of the closest tile from the selected one in the direction of `dx`/`dy`
+    /// (each -1, 0 or 1), or of the first tile when the selected coin has none
+    pub fn neighbor(&self, dx: i32, dy: i32) -> Option<usize> {
+        let Some((_, current)) = self.tiles.iter().find(|(i, _)| *i == self.selected) else {
+            return self.tiles.first().map(|(i, _)| *i);
+        };
+        // Centers in doubled coordinates to stay in integers
+        let center = |r: &Rect| (2 * r.x as i32 + r.width as i32, 2 * r.y as i32 + r.height as i32);
+        let (cx, cy) = center(current);
+
+        self.tiles
+            .iter()
+            .filter_map(|(i, tile)| {
+                let gap = match (dx, dy) {
+                    (1, _) => tile.left() as i32 - current.right() as i32,
+                    (-1, _) => current.left() as i32 - tile.right() as i32,
+                    (_, 1) => tile.top() as i32 - current.bottom() as i32,
+                    (_, -1) => current.top() as i32 - tile.bottom() as i32,
+                    _ => return None,
+                };
+                if gap < 0 {
+                    return None;
+                }
+                let (tx, ty) = center(tile);
+                let off_axis = if dx != 0 { (ty - cy).abs() } else { (tx - cx).abs() };
+                Some((gap * 4 + off_axis, *i))
+            })
+            .min()
+            .map(|(_, i)| i)
+    }
+}
+
+/// Treemap of coins sized by market cap and colored by their change over a timeframe,
+/// stronger colors meaning bigger moves
+#[derive(Debug, Clone)]
+pub struct Heatmap<'a> {
+    coins: &'a [CoinMarket],
+    timeframe: ChartTimeframe,
+    title: Option<Line<'a>>,
+    style: WidgetStyle,
+}
+
+impl<'a> Heatmap<'a> {
+    /// Heatmap of `coins` colored by their 24 hour change
+    pub fn new(coins: &'a [CoinMarket]) -> Self {
+        Self {
+            coins,
+            timeframe: ChartTimeframe::TwentyFourHours,
+            title: None,
+            style: WidgetStyle::default(),
+        }
+    }
+
+    /// Timeframe whose change colors the tiles
+    pub fn timeframe(mut self, timeframe: ChartTimeframe) -> Self {
+        self.timeframe = timeframe;
+        self
+    }
+
+    /// Replace the default `Market Heatmap (24H)` title
+    pub fn title(mut self, title: impl Into<Line<'a>>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Colors of the heatmap
+    pub fn style(mut self, style: WidgetStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Background of a tile: from the neutral selection background towards the
+    /// up or down color as the change grows
+    fn tile_color(&self, change: Option<f64>) -> Color {
+        let style = self.style;
+        let Some(change) = change else {
+            return style.selected_bg;
+        };
+        // Change that gets the full color
+        let full = match self.timeframe {
+            ChartTimeframe::OneHour => 1.0,
+            ChartTimeframe::TwentyFourHours => 5.0,
+            ChartTimeframe::SevenDays => 10.0,
+        };
+        let strength = (change.abs() / full).min(1.0);
+        let target = style.change_color(Some(change));
+
+        match (style.selected_bg, target) {
+            (Color::Rgb(r0, g0, b0), Color::Rgb(r1, g1, b1)) => {
+                let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * (0.35 + 0.65 * strength)) as u8;
+                Color::Rgb(mix(r0, r1), mix(g0, g1), mix(b0, b1))
+            }
+            _ => target,
+        }
+    }
+}
+
+/// Dark or light text, whichever reads better on `background`
+fn text_on(background: Color, fallback: Color) -> Color {
+    match background {
+        Color::Rgb(r, g, b) if 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64 > 140.0 => Color::Black,
+        Color::Rgb(..) => Color::White,
+        _ => fallback,
+    }
+}
+
+impl StatefulWidget for Heatmap<'_> {
+    type State = HeatmapState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let style = self.style;
+        let title = self
+            .title
+            .clone()
+            .unwrap_or_else(|| Line::from(format!(" Market Heatmap ({}) ", self.timeframe.label())));
+        let block = style.block(title);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let mut weighted: Vec<(usize, f64)> = self
+            .coins
+            .iter()
+            .enumerate()
+            .filter_map(|(i, coin)| coin.market_cap.filter(|cap| *cap > 0.0).map(|cap| (i, cap)))
+            .collect();
+        weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        // Show as many coins as can each get a readable tile
+        let cells = inner.width as f64 * inner.height as f64;
+        let mut total = 0.0;
+        let count = weighted
+            .iter()
+            .take_while(|(_, weight)| {
+                total += weight;
+                weight / total * cells >= MIN_TILE_CELLS
+            })
+            .count();
+        weighted.truncate(count);
+
+        // Cells are about twice as tall as wide, so lay out in square units
+        let weights: Vec<f64> = weighted.iter().map(|(_, w)| *w).collect();
+        let layout = squarify(
+            &weights,
+            TreemapRect {
+                x: 0.0,
+                y: 0.0,
+                width: inner.width as f64,
+                height: inner.height as f64 * 2.0,
+            },
+        );
+
+        state.tiles = weighted
+            .iter()
+            .zip(layout)
+            .filter_map(|((i, _), r)| {
+                let x0 = r.x.round() as u16;
+                let x1 = (r.x + r.width).round() as u16;
+                let y0 = (r.y / 2.0).round() as u16;
+                let y1 = ((r.y + r.height) / 2.0).round() as u16;
+                (x1 > x0 && y1 > y0).then(|| (*i, Rect::new(inner.x + x0, inner.y + y0, x1 - x0, y1 - y0)))
+            })
+            .collect();
+
+        for (i, tile) in &state.tiles {
+            let coin = &self.coins[*i];
+            let change = self.timeframe.change(coin);
+            let background = self.tile_color(change);
+            let mut tile_style = Style::default().bg(background).fg(text_on(background, style.text));
+            if *i == state.selected {
+                tile_style = tile_style.add_modifier(Modifier::REVERSED | Modifier::BOLD);
+            }
+
+            // Leave a gap on the right so neighbouring tiles of the same color stay apart
+            let fill = if tile.width >= 3 {
+                Rect { width: tile.width - 1, ..*tile }
+            } else {
+                *tile
+            };
+            buf.set_style(fill, tile_style);
+
+            let mut labels = vec![coin.symbol.to_uppercase(), style.percentage(change)];
+            if let Some(price) = coin.current_price {
+                labels.push(format_price(price));
+            }
+            // The symbol may be cut short, but numbers are shown whole or not at all, change first
+            let width = fill.width as usize;
+            labels = labels
+                .into_iter()
+                .enumerate()
+                .take_while(|(row, label)| *row == 0 || label.chars().count() <= width)
+                .map(|(_, label)| label)
+                .collect();
+            labels.truncate(fill.height as usize);
+            let top = fill.y + (fill.height - labels.len() as u16) / 2;
+            for (row, label) in labels.iter().enumerate() {
+                let label: String = label.chars().take(fill.width as usize).collect();
+                let x = fill.x + (fill.width - label.chars().count() as u16) / 2;
+                buf.set_string(x, top + row as u16, label, tile_style);
+            }
+        }
+    }
+}
+
+/// Line chart of prices, oldest first, with min/max labels on the y axis
+#[derive(Debug, Clone)]
+pub struct PriceChart<'a> {
+    prices: &'a [f64],
+    highlight: Option<usize>,
+    title: Line<'a>,
+    empty_message: &'a str,
+    style: WidgetStyle,
+}
+
+impl<'a> PriceChart<'a> {
+    /// Chart of evenly spaced `prices`
+    pub fn new(prices: &'a [f64]) -> Self {
+        Self {
+            prices,
+            highlight: None,
+            title: Line::from(" Price Chart "),
+            empty_message: "No chart data available",
+            style: WidgetStyle::default(),
+        }
+    }
+
+    /// Title shown in the border
+    pub fn title(mut self, title: impl Into<Line<'a>>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Mark the price at this index with a vertical line
+    pub fn highlight(mut self, index: Option<usize>) -> Self {
+        self.highlight = index;
+        self
+    }
+
+    /// Index of the price drawn in column `x` when the chart is rendered in `area`
+    pub fn index_at(&self, area: Rect, x: u16) -> Option<usize> {
+        let plot = self.plot_area(area)?;
+        if x < plot.left() || x >= plot.right() {
+            return None;
+        }
+        // Nearest point to the middle of the column
+        let offset = (x - plot.x) as f64 + 0.5;
+        let index = (offset * self.prices.len() as f64 / plot.width as f64).round() as usize;
+        Some(index.min(self.prices.len() - 1))
+    }
+
+    /// Lowest and highest price
+    fn range(&self) -> (f64, f64) {
+        let min_price = self.prices.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max_price = self.prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        (min_price, max_price)
+    }
+
+    /// Part of `area` the line is drawn in: inside the border, right of the y-axis labels and line
+    fn plot_area(&self, area: Rect) -> Option<Rect> {
+        if self.prices.is_empty() {
+            return None;
+        }
+        let (min_price, max_price) = self.range();
+        let labels = format!("{:.0}", min_price).len().max(format!("{:.0}", max_price).len()) as u16;
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        let left = inner.x.saturating_add(labels + 1);
+        (left < inner.right()).then(|| Rect::new(left, inner.y, inner.right() - left, inner.height))
+    }
+
+    /// Text shown instead of the chart when there are no prices
+    pub fn empty_message(mut self, message: &'a str) -> Self {
+        self.empty_message = message;
+        self
+    }
+
+    /// Colors of the chart
+    pub fn style(mut self, style: WidgetStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl Widget for PriceChart<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = self.style;
+        let (min_price, max_price) = self.range();
+        let block = style.block(self.title);
+
+        if self.prices.is_empty() {
+            Paragraph::new(self.empty_message)
+                .style(Style::default().fg(style.muted))
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+
+        let data: Vec<(f64, f64)> = self
+            .prices
+            .iter()
+            .enumerate()
+            .map(|(i, &price)| (i as f64, price))
+            .collect();
+
+        let padding = (max_price - min_price) * 0.1;
+
+        let marker: Vec<(f64, f64)> = self
+            .highlight
+            .filter(|i| *i < data.len())
+            .map(|i| vec![(i as f64, min_price - padding), (i as f64, max_price + padding)])
+            .unwrap_or_default();
+        let datasets = vec![
+            Dataset::default()
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(style.muted))
+                .data(&marker),
+            Dataset::default()
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(style.chart))
+                .data(&data),
+        ];
+
+        Chart::new(datasets)
+            .block(block)
+            .x_axis(
+                Axis::default()
+                    .style(Style::default().fg(style.muted))
+                    .bounds([0.0, data.len() as f64]),
+            )
+            .y_axis(
+                Axis::default()
+                    .style(Style::default().fg(style.muted))
+                    .bounds([min_price - padding, max_price + padding])
+                    .labels(vec![
+                        Span::raw(format!("{:.0}", min_price)),
+                        Span::raw(format!("{:.0}", max_price)),
+                    ]),
+            )
+            .render(area, buf);
+    }
+}
+
+/// Current price with the 24h change, high and low
+#[derive(Debug, Clone)]
+pub struct PricePanel<'a> {
+    coin: Option<&'a CoinMarket>,
+    vs_currency: &'a str,
+    style: WidgetStyle,
+}
+
+/// Price changes over 1h, 24h, 7d, 14d, 30d, 60d, 200d and 1y
+#[derive(Debug, Clone)]
+pub struct ChangesPanel<'a> {
+    coin: Option<&'a CoinMarket>,
+    style: WidgetStyle,
+}
+
+/// Name, rank, market cap, volume, all-time high/low and last update
+#[derive(Debug, Clone)]
+pub struct DetailsPanel<'a> {
+    coin: Option<&'a CoinMarket>,
+    vs_currency: &'a str,
+    style: WidgetStyle,
+}
+
+/// Circulating, total and max supply with a progress bar
+#[derive(Debug, Clone)]
+pub struct SupplyPanel<'a> {
+    coin: Option<&'a CoinMarket>,
+    style: WidgetStyle,
+}
+
+impl<'a> PricePanel<'a> {
+    /// Panel for `coin` quoted in USD; `None` shows a placeholder
+    pub fn new(coin: Option<&'a CoinMarket>) -> Self {
+        Self {
+            coin,
+            vs_currency: "usd",
+            style: WidgetStyle::default(),
+        }
+    }
+
+    /// Quote currency of the prices
+    pub fn currency(mut self, vs_currency: &'a str) -> Self {
+        self.vs_currency = vs_currency;
+        self
+    }
+
+    /// Colors of the panel
+    pub fn style(mut self, style: WidgetStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl<'a> ChangesPanel<'a> {
+    /// Panel for `coin`; `None` shows a placeholder
+    pub fn new(coin: Option<&'a CoinMarket>) -> Self {
+        Self {
+            coin,
+            style: WidgetStyle::default(),
+        }
+    }
+
+    /// Colors of the panel
+    pub fn style(mut self, style: WidgetStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl<'a> DetailsPanel<'a> {
+    /// Panel for `coin` quoted in USD; `None` shows a placeholder
+    pub fn new(coin: Option<&'a CoinMarket>) -> Self {
+        Self {
+            coin,
+            vs_currency: "usd",
+            style: WidgetStyle::default(),
+        }
+    }
+
+    /// Quote currency of the amounts
+    pub fn currency(mut self, vs_currency: &'a str) -> Self {
+        self.vs_currency = vs_currency;
+        self
+    }
+
+    /// Colors of the panel
+    pub fn style(mut self, style: WidgetStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl<'a> SupplyPanel<'a> {
+    /// Panel for `coin`; `None` shows a placeholder
+    pub fn new(coin: Option<&'a CoinMarket>) -> Self {
+        Self {
+            coin,
+            style: WidgetStyle::default(),
+        }
+    }
+
+    /// Colors of the panel
+    pub fn style(mut self, style: WidgetStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl Widget for PricePanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = self.style;
+        let content = if let Some(coin) = self.coin {
+            let price = coin.current_price.unwrap_or(0.0);
+            let change = coin.price_change_percentage_24h.unwrap_or(0.0);
+            let money = |value: Option<f64>| money(format!("{:.2}", value.unwrap_or(0.0)), self.vs_currency);
+
+            vec![
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} ", coin.symbol.to_uppercase()),
+                        Style::default().fg(style.header).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(coin.name.as_str(), Style::default().fg(style.text)),
+                ]),
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} ", money(Some(price))),
+                        Style::default().fg(style.text).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        style.percentage(Some(change)),
+                        Style::default().fg(style.change_color(Some(change))),
+                    ),
+                ]),
+                Line::from(vec![
+                    style.label("H: "),
+                    Span::styled(format!("{} ", money(coin.high_24h)), Style::default().fg(style.positive)),
+                    style.label("L: "),
+                    Span::styled(money(coin.low_24h), Style::default().fg(style.negative)),
+                ]),
+            ]
+        } else {
+            no_coin()
+        };
+
+        let title = format!(" Live Price ({}) ", self.vs_currency.to_uppercase());
+        Paragraph::new(content).block(style.block(title)).render(area, buf);
+    }
+}
+
+impl Widget for ChangesPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = self.style;
+        let content = if let Some(coin) = self.coin {
+            [
+                ("1H", coin.price_change_percentage_1h_in_currency),
+                ("24H", coin.price_change_percentage_24h_in_currency),
+                ("7D", coin.price_change_percentage_7d_in_currency),
+                ("14D", coin.price_change_percentage_14d_in_currency),
+                ("30D", coin.price_change_percentage_30d_in_currency),
+                ("60D", coin.price_change_percentage_60d_in_currency),
+                ("200D", coin.price_change_percentage_200d_in_currency),
+                ("1Y", coin.price_change_percentage_1y_in_currency),
+            ]
+            .into_iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{:5} ", label), Style::default().fg(style.muted)),
+                    Span::styled(style.percentage(value), Style::default().fg(style.change_color(value))),
+                ])
+            })
+            .collect()
+        } else {
+            no_coin()
+        };
+
+        Paragraph::new(content).block(style.block(" Changes ")).render(area, buf);
+    }
+}
+
+impl Widget for DetailsPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = self.style;
+        let content = if let Some(coin) = self.coin {
+            let format_date = |date: &Option<String>| -> String {
+                date.as_ref()
+                    .map(|d| d.chars().take(16).collect::<String>().replace('T', " "))
+                    .unwrap_or_else(|| "N/A".to_string())
+            };
+            let amount = |value: String| match currency_sign(self.vs_currency) {
+                Some(sign) => format!("{} {} {}", value, self.vs_currency.to_uppercase(), sign),
+                None => format!("{} {}", value, self.vs_currency.to_uppercase()),
+            };
+            let row = |label: &'static str, value: String, color: Color| {
+                Line::from(vec![style.label(label), Span::styled(value, Style::default().fg(color))])
+            };
+
+            vec![
+                row("Name        ", coin.name.clone(), style.text),
+                row("Symbol      ", coin.symbol.to_uppercase(), style.text),
+                row(
+                    "Rank        ",
+                    coin.market_cap_rank.map(|r| r.to_string()).unwrap_or_else(|| "N/A".to_string()),
+                    style.header,
+                ),
+                row(
+                    "MarketCap   ",
+                    amount(format_large_number(coin.market_cap.unwrap_or(0.0))),
+                    style.text,
+                ),
+                row("ATH         ", amount(format_large_number(coin.ath.unwrap_or(0.0))), style.positive),
+                row("ATHDate     ", format_date(&coin.ath_date), style.muted),
+                row("ATL         ", amount(format_price(coin.atl.unwrap_or(0.0))), style.negative),
+                row("ATLDate     ", format_date(&coin.atl_date), style.muted),
+                row(
+                    "TotalVolume ",
+                    amount(format_large_number(coin.total_volume.unwrap_or(0.0))),
+                    style.text,
+                ),
+                row("LastUpdate  ", format_date(&coin.last_updated), style.muted),
+            ]
+        } else {
+            no_coin()
+        };
+
+        Paragraph::new(content).block(style.block(" Details ")).render(area, buf);
+    }
+}
+
+impl Widget for SupplyPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = self.style;
+        let content = if let Some(coin) = self.coin {
+            let circulating = coin.circulating_supply.unwrap_or(0.0);
+            let total = coin.total_supply.unwrap_or(0.0);
+            let max = coin.max_supply;
+
+            // Share of the max supply in circulation, or of the total supply when uncapped
+            let supply_ratio = match max {
+                Some(max_supply) if max_supply > 0.0 => (circulating / max_supply * 100.0).min(100.0),
+                Some(_) => 0.0,
+                None if total > 0.0 => (circulating / total * 100.0).min(100.0),
+                None => 0.0,
+            };
+
+            let bar_width = 20;
+            let filled = (supply_ratio / 100.0 * bar_width as f64) as usize;
+            let bar = format!("{}{}", "█".repeat(filled), "░".repeat(bar_width - filled));
+
+            vec![
+                Line::from(vec![
+                    style.label("Circulating "),
+                    Span::styled(format_large_number(circulating), Style::default().fg(style.positive)),
+                ]),
+                Line::from(vec![
+                    style.label("Total       "),
+                    Span::styled(format_large_number(total), Style::default().fg(style.text)),
+                ]),
+                Line::from(vec![
+                    style.label("Max Supply  "),
+                    Span::styled(
+                        max.map(format_large_number).unwrap_or_else(|| "∞ Unlimited".to_string()),
+                        Style::default().fg(style.header),
+                    ),
+                ]),
+                Line::from(vec![
+                    Span::styled(bar, Style::default().fg(style.chart)),
+                    Span::styled(format!(" {:.1}%", supply_ratio), Style::default().fg(style.text)),
+                ]),
+            ]
+        } else {
+            no_coin()
+        };
+
+        Paragraph::new(content).block(style.block(" Supply ")).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_columns_drops_by_priority() {
+        let columns: Vec<(Column, u16)> = Column::DEFAULT.iter().map(|c| (*c, 10)).collect();
+        let fit = |available| -> Vec<Column> {
+            fit_columns(columns.clone(), available).into_iter().map(|(c, _)| c).collect()
+        };
+        let field = Column::Field;
+
+        // Seven columns of 10 with six gaps
+        assert_eq!(fit(76), Column::DEFAULT);
+        assert_eq!(
+            fit(75),
+            [Field::Rank, Field::Symbol, Field::Price, Field::Change24h, Field::Change7d, Field::MarketCap].map(field)
+        );
+        assert_eq!(fit(54), [Field::Rank, Field::Symbol, Field::Price, Field::Change24h, Field::MarketCap].map(field));
+        assert_eq!(fit(43), [Field::Rank, Field::Symbol, Field::Price, Field::Change24h].map(field));
+        assert_eq!(fit(32), [Field::Symbol, Field::Price, Field::Change24h].map(field));
+        assert_eq!(fit(21), [Field::Symbol, Field::Price].map(field));
+        assert_eq!(fit(10), [field(Field::Symbol)]);
+        // The last column stays even when it doesn't fit
+        assert_eq!(fit(0), [field(Field::Symbol)]);
+    }
+
+    #[test]
+    fn fit_columns_drops_the_rightmost_of_equal_priority() {
+        let columns = vec![
+            (Column::Field(Field::Name), 5),
+            (Column::Field(Field::Symbol), 5),
+            (Column::Field(Field::Id), 5),
+        ];
+        let kept: Vec<Column> = fit_columns(columns, 11).into_iter().map(|(c, _)| c).collect();
+        assert_eq!(kept, [Column::Field(Field::Name), Column::Field(Field::Symbol)]);
+    }
+
+    /// ```text
+    /// +----+----------+
+    /// |    |    1     |
+    /// | 0  +------+---+
+    /// |    |  2   | 3 |
+    /// +----+------+---+
+    /// ```
+    fn heatmap(selected: usize) -> HeatmapState {
+        HeatmapState {
+            selected,
+            tiles: vec![
+                (0, Rect::new(0, 0, 10, 10)),
+                (1, Rect::new(10, 0, 10, 4)),
+                (2, Rect::new(10, 4, 6, 6)),
+                (3, Rect::new(16, 4, 4, 6)),
+            ],
+        }
+    }
+
+    #[test]
+    fn heatmap_moves_to_the_adjacent_tile() {
+        assert_eq!(heatmap(0).neighbor(1, 0), Some(2));
+        assert_eq!(heatmap(2).neighbor(1, 0), Some(3));
+        assert_eq!(heatmap(3).neighbor(-1, 0), Some(2));
+        assert_eq!(heatmap(1).neighbor(-1, 0), Some(0));
+        assert_eq!(heatmap(2).neighbor(0, -1), Some(1));
+        assert_eq!(heatmap(3).neighbor(0, -1), Some(1));
+        assert_eq!(heatmap(1).neighbor(0, 1), Some(2));
+    }
+
+    #[test]
+    fn heatmap_stops_at_the_edges() {
+        assert_eq!(heatmap(0).neighbor(-1, 0), None);
+        assert_eq!(heatmap(0).neighbor(0, 1), None);
+        assert_eq!(heatmap(3).neighbor(1, 0), None);
+        assert_eq!(heatmap(1).neighbor(0, -1), None);
+        assert_eq!(heatmap(1).neighbor(0, 0), None);
+    }
+
+    #[test]
+    fn heatmap_starts_at_the_first_tile() {
+        assert_eq!(heatmap(7).neighbor(1, 0), Some(0));
+        assert_eq!(HeatmapState::default().neighbor(1, 0), None);
+    }
+}